@@ -4,25 +4,47 @@ use crate::handlers::dialogue::{
     instructions, receive_confirm, receive_database_id, receive_integration_token, State,
 };
 use crate::handlers::message::message_handler;
-use crate::img_push::ImgPush;
+use crate::handlers::recent::{
+    is_retag_reply, receive_tags, recent_callback, RetagPrompts, RECENT_CALLBACK_PREFIX,
+};
+use crate::handlers::search::{search_callback, SearchQueries, SEARCH_CALLBACK_PREFIX};
+use crate::handlers::settings::{send_settings, settings_callback, SETTINGS_CALLBACK_PREFIX};
+use crate::notion::NotionClients;
+use crate::storage::ImageHost;
 use std::sync::Arc;
 use teloxide::dispatching::dialogue::InMemStorage;
 use teloxide::prelude::*;
 
-pub async fn run_bot(db: Arc<Database>, img_push: Arc<ImgPush>) {
-    let bot = Bot::from_env();
-
-    let handler = Update::filter_message()
+pub async fn run_bot(
+    bot: Bot,
+    db: Arc<Database>,
+    image_host: Arc<ImageHost>,
+    notion_clients: Arc<NotionClients>,
+) {
+    let messages = Update::filter_message()
         .enter_dialogue::<Message, InMemStorage<State>, State>()
         .branch(
             dptree::entry()
                 .filter_command::<Command>()
+                // the settings depend on how the bot is set up, e.g. whether OCR is available
+                .branch(dptree::case![Command::Settings].endpoint(send_settings))
                 .endpoint(handle_command),
         )
+        .branch(
+            dptree::filter(|msg: Message, retag_prompts: Arc<RetagPrompts>| {
+                is_retag_reply(&msg, &retag_prompts)
+            })
+            .endpoint(receive_tags),
+        )
         .branch(
             dptree::filter(|msg: Message, db: Arc<Database>| {
-                let user_details = db.get(&msg.chat.id.to_string()).unwrap();
-                user_details.is_some()
+                match db.get(&msg.chat.id.to_string()) {
+                    Ok(user_details) => user_details.is_some(),
+                    Err(err) => {
+                        log::error!("could not load user of chat {}: {err}", msg.chat.id);
+                        false
+                    }
+                }
             })
             .endpoint(message_handler),
         )
@@ -40,8 +62,40 @@ pub async fn run_bot(db: Arc<Database>, img_push: Arc<ImgPush>) {
             .endpoint(receive_confirm),
         );
 
+    let callbacks = Update::filter_callback_query()
+        .branch(
+            dptree::filter(|q: CallbackQuery| {
+                q.data
+                    .is_some_and(|data| data.starts_with(SETTINGS_CALLBACK_PREFIX))
+            })
+            .endpoint(settings_callback),
+        )
+        .branch(
+            dptree::filter(|q: CallbackQuery| {
+                q.data
+                    .is_some_and(|data| data.starts_with(SEARCH_CALLBACK_PREFIX))
+            })
+            .endpoint(search_callback),
+        )
+        .branch(
+            dptree::filter(|q: CallbackQuery| {
+                q.data
+                    .is_some_and(|data| data.starts_with(RECENT_CALLBACK_PREFIX))
+            })
+            .endpoint(recent_callback),
+        );
+
+    let handler = dptree::entry().branch(messages).branch(callbacks);
+
     Dispatcher::builder(bot, handler)
-        .dependencies(dptree::deps![InMemStorage::<State>::new(), db, img_push])
+        .dependencies(dptree::deps![
+            InMemStorage::<State>::new(),
+            db,
+            image_host,
+            notion_clients,
+            Arc::new(SearchQueries::default()),
+            Arc::new(RetagPrompts::default())
+        ])
         .enable_ctrlc_handler()
         .build()
         .dispatch()
@@ -23,3 +23,14 @@ These steps are explained again here:
 // __Help__
 // Use the command `/help` to show this message
 ";
+
+pub const TEMPLATE_EXAMPLE_MSG: &str = r#"You have no template yet, set one like this:
+/template {
+  "properties": { "Status": "Inbox", "Source": "{url}" },
+  "blocks": [
+    { "type": "heading", "level": 2, "text": "Notes on {title}" },
+    { "type": "todo", "text": "Read by {date}" },
+    { "type": "divider" }
+  ]
+}
+Blocks can be heading, paragraph, todo, bulleted_list_item, numbered_list_item, quote and divider. {title}, {url}, {date} and {tags} are replaced."#;
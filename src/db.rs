@@ -1,12 +1,249 @@
-use std::sync::Mutex;
+use std::{str::FromStr, sync::Mutex};
 
+use chrono::{NaiveDate, Utc};
+use chrono_tz::Tz;
 use rusqlite::{params, Connection, Result};
 
+use crate::notion::PageTemplate;
+use crate::tag_rules::{RuleKind, TagRule};
+
 #[derive(Clone, Default, PartialEq, Debug)]
 pub struct UserDetails {
     pub user_id: String,
     pub integration_token: String,
-    pub database_id: String,
+    pub target: Target,
+}
+
+/// Where the messages of a user are saved, with the id of the database or page.
+#[derive(Clone, PartialEq, Debug)]
+pub enum Target {
+    /// a page is created in the database for each message
+    Database(String),
+    /// the messages are appended to the page
+    Page(String),
+    /// the messages are appended to the page of the day in the database
+    Journal(String),
+}
+
+impl Default for Target {
+    fn default() -> Self {
+        Target::Database(String::new())
+    }
+}
+
+impl Target {
+    pub fn id(&self) -> &str {
+        match self {
+            Target::Database(id) | Target::Page(id) | Target::Journal(id) => id,
+        }
+    }
+
+    /// The database the pages are created in, `None` when appending to a page.
+    pub fn database_id(&self) -> Option<&str> {
+        match self {
+            Target::Database(id) | Target::Journal(id) => Some(id),
+            Target::Page(_) => None,
+        }
+    }
+
+    pub fn kind(&self) -> &'static str {
+        match self {
+            Target::Database(_) => "database",
+            Target::Page(_) => "page",
+            Target::Journal(_) => "journal",
+        }
+    }
+
+    /// The target stored as kind and id, e.g. `page` and the id of the page.
+    pub fn from_parts(kind: &str, id: String) -> std::result::Result<Self, String> {
+        match kind {
+            "database" => Ok(Target::Database(id)),
+            "page" => Ok(Target::Page(id)),
+            "journal" => Ok(Target::Journal(id)),
+            other => Err(format!("unknown target {other}")),
+        }
+    }
+}
+
+/// Maps the kind column of a target to a sql error when it is unknown.
+fn target_from_row(kind: String, id: String) -> Result<Target> {
+    Target::from_parts(&kind, id).map_err(|err| {
+        rusqlite::Error::FromSqlConversionFailure(0, rusqlite::types::Type::Text, err.into())
+    })
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum ReplyVerbosity {
+    /// no reply when the page was created
+    Quiet,
+    /// reply with the link to the page
+    Normal,
+    /// reply with the link and what was saved
+    Verbose,
+}
+
+impl ReplyVerbosity {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ReplyVerbosity::Quiet => "quiet",
+            ReplyVerbosity::Normal => "normal",
+            ReplyVerbosity::Verbose => "verbose",
+        }
+    }
+
+    pub fn next(&self) -> Self {
+        match self {
+            ReplyVerbosity::Quiet => ReplyVerbosity::Normal,
+            ReplyVerbosity::Normal => ReplyVerbosity::Verbose,
+            ReplyVerbosity::Verbose => ReplyVerbosity::Quiet,
+        }
+    }
+}
+
+impl FromStr for ReplyVerbosity {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        match s {
+            "quiet" => Ok(ReplyVerbosity::Quiet),
+            "normal" => Ok(ReplyVerbosity::Normal),
+            "verbose" => Ok(ReplyVerbosity::Verbose),
+            other => Err(format!("unknown reply verbosity {other}")),
+        }
+    }
+}
+
+#[derive(Clone, PartialEq, Debug)]
+pub struct UserSettings {
+    pub timezone: Tz,
+    /// date property that is filled with dates found in messages
+    pub date_property: Option<String>,
+    pub add_bookmark: bool,
+    /// add the whole message as text to the page
+    pub include_full_text: bool,
+    /// tags that are not an option of the Tags property yet are dropped otherwise
+    pub create_unknown_tags: bool,
+    pub mentions_as_tags: bool,
+    /// show a link preview of the created page in the reply
+    pub link_preview: bool,
+    pub reply_verbosity: ReplyVerbosity,
+    /// add the text recognized in images to the page, if OCR is set up
+    pub ocr: bool,
+    /// add the text of linked articles to the page
+    pub extract_articles: bool,
+    /// store a snapshot of linked pages
+    pub archive_pages: bool,
+    /// sections and property values added to every new page
+    pub template: Option<PageTemplate>,
+}
+
+impl Default for UserSettings {
+    fn default() -> Self {
+        Self {
+            timezone: Tz::UTC,
+            date_property: None,
+            add_bookmark: true,
+            include_full_text: false,
+            create_unknown_tags: true,
+            mentions_as_tags: false,
+            link_preview: true,
+            reply_verbosity: ReplyVerbosity::Normal,
+            ocr: true,
+            extract_articles: false,
+            archive_pages: false,
+            template: None,
+        }
+    }
+}
+
+impl UserSettings {
+    pub fn today(&self) -> NaiveDate {
+        Utc::now().with_timezone(&self.timezone).date_naive()
+    }
+
+    /// Settings as stored in the database, `None` values are removed.
+    fn to_values(&self) -> Vec<(&'static str, Option<String>)> {
+        vec![
+            ("timezone", Some(self.timezone.name().to_string())),
+            ("date_property", self.date_property.clone()),
+            ("add_bookmark", Some(self.add_bookmark.to_string())),
+            (
+                "include_full_text",
+                Some(self.include_full_text.to_string()),
+            ),
+            (
+                "create_unknown_tags",
+                Some(self.create_unknown_tags.to_string()),
+            ),
+            ("mentions_as_tags", Some(self.mentions_as_tags.to_string())),
+            ("link_preview", Some(self.link_preview.to_string())),
+            (
+                "reply_verbosity",
+                Some(self.reply_verbosity.as_str().to_string()),
+            ),
+            ("ocr", Some(self.ocr.to_string())),
+            ("extract_articles", Some(self.extract_articles.to_string())),
+            ("archive_pages", Some(self.archive_pages.to_string())),
+            (
+                "template",
+                self.template
+                    .as_ref()
+                    .and_then(|template| serde_json::to_string(template).ok()),
+            ),
+        ]
+    }
+
+    /// Invalid values are ignored and the default is kept.
+    fn set_value(&mut self, key: &str, value: String) {
+        let flag = |value: &str| value.parse::<bool>().ok();
+
+        match key {
+            "timezone" => {
+                if let Ok(timezone) = value.parse() {
+                    self.timezone = timezone;
+                }
+            }
+            "date_property" => self.date_property = Some(value),
+            "add_bookmark" => self.add_bookmark = flag(&value).unwrap_or(self.add_bookmark),
+            "include_full_text" => {
+                self.include_full_text = flag(&value).unwrap_or(self.include_full_text)
+            }
+            "create_unknown_tags" => {
+                self.create_unknown_tags = flag(&value).unwrap_or(self.create_unknown_tags)
+            }
+            "mentions_as_tags" => {
+                self.mentions_as_tags = flag(&value).unwrap_or(self.mentions_as_tags)
+            }
+            "link_preview" => self.link_preview = flag(&value).unwrap_or(self.link_preview),
+            "reply_verbosity" => {
+                if let Ok(reply_verbosity) = value.parse() {
+                    self.reply_verbosity = reply_verbosity;
+                }
+            }
+            "ocr" => self.ocr = flag(&value).unwrap_or(self.ocr),
+            "extract_articles" => {
+                self.extract_articles = flag(&value).unwrap_or(self.extract_articles)
+            }
+            "archive_pages" => self.archive_pages = flag(&value).unwrap_or(self.archive_pages),
+            "template" => match serde_json::from_str(&value) {
+                Ok(template) => self.template = Some(template),
+                Err(err) => log::warn!("ignoring invalid template: {err}"),
+            },
+            _ => log::warn!("ignoring unknown setting {key}"),
+        }
+    }
+}
+
+#[derive(Clone, PartialEq, Debug)]
+pub struct OutboxEntry {
+    pub id: i64,
+    pub user_id: String,
+    /// the target when the message was sent, the user might change it before the retry
+    pub target: Target,
+    /// serialized capture of the message
+    pub capture: String,
+    pub attempts: u32,
+    pub last_error: Option<String>,
 }
 
 pub struct Database {
@@ -27,36 +264,45 @@ impl Database {
         let conn = self.conn.lock().unwrap();
 
         conn.execute(
-            "INSERT OR REPLACE INTO user_details (user_id, integration_token, database_id)
-             VALUES (?,?,?)",
+            "INSERT OR REPLACE INTO user_details (user_id, integration_token, database_id, target)
+             VALUES (?,?,?,?)",
             params![
                 user_details.user_id,
                 user_details.integration_token,
-                user_details.database_id
+                user_details.target.id(),
+                user_details.target.kind()
             ],
         )?;
 
         Ok(())
     }
 
+    /// Deletes the user with their tagging rules, settings and pending captures.
     pub fn delete(&self, user_id: &str) -> Result<()> {
-        let conn = self.conn.lock().unwrap();
+        let mut conn = self.conn.lock().unwrap();
+        let tx = conn.transaction()?;
 
-        conn.execute(
+        tx.execute(
             "DELETE
             FROM user_details
             WHERE user_id = ?1;",
             params![user_id],
         )?;
+        for table in ["tag_rules", "user_settings", "outbox"] {
+            tx.execute(
+                &format!("DELETE FROM {table} WHERE user_id = ?1;"),
+                params![user_id],
+            )?;
+        }
 
-        Ok(())
+        tx.commit()
     }
 
     pub fn get(&self, user_id: &str) -> Result<Option<UserDetails>> {
         let conn = self.conn.lock().unwrap();
 
         let mut stmt = conn.prepare(
-            "SELECT user_id, integration_token, database_id
+            "SELECT user_id, integration_token, database_id, target
             FROM user_details
             WHERE user_id = ?1;",
         )?;
@@ -65,7 +311,7 @@ impl Database {
             Ok(UserDetails {
                 user_id: row.get(0)?,
                 integration_token: row.get(1)?,
-                database_id: row.get(2)?,
+                target: target_from_row(row.get(3)?, row.get(2)?)?,
             })
         });
 
@@ -75,6 +321,201 @@ impl Database {
             Err(e) => Err(e),
         }
     }
+
+    pub fn get_settings(&self, user_id: &str) -> Result<UserSettings> {
+        let conn = self.conn.lock().unwrap();
+
+        let mut stmt = conn.prepare(
+            "SELECT key, value
+            FROM user_settings
+            WHERE user_id = ?1;",
+        )?;
+
+        let rows = stmt
+            .query_map(params![user_id], |row| {
+                Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?))
+            })?
+            .collect::<Result<Vec<(String, String)>>>()?;
+
+        let mut settings = UserSettings::default();
+        for (key, value) in rows {
+            settings.set_value(&key, value);
+        }
+
+        Ok(settings)
+    }
+
+    pub fn save_settings(&self, user_id: &str, settings: &UserSettings) -> Result<()> {
+        let mut conn = self.conn.lock().unwrap();
+        let tx = conn.transaction()?;
+
+        for (key, value) in settings.to_values() {
+            match value {
+                Some(value) => tx.execute(
+                    "INSERT OR REPLACE INTO user_settings (user_id, key, value)
+                     VALUES (?,?,?)",
+                    params![user_id, key, value],
+                )?,
+                None => tx.execute(
+                    "DELETE
+                    FROM user_settings
+                    WHERE user_id = ?1 AND key = ?2;",
+                    params![user_id, key],
+                )?,
+            };
+        }
+
+        tx.commit()
+    }
+
+    pub fn add_to_outbox(
+        &self,
+        user_id: &str,
+        target: &Target,
+        capture: &str,
+        next_attempt_at: i64,
+        error: &str,
+    ) -> Result<i64> {
+        let conn = self.conn.lock().unwrap();
+
+        conn.execute(
+            "INSERT INTO outbox (user_id, database_id, target, capture, attempts, next_attempt_at, last_error)
+             VALUES (?,?,?,?,0,?,?)",
+            params![
+                user_id,
+                target.id(),
+                target.kind(),
+                capture,
+                next_attempt_at,
+                error
+            ],
+        )?;
+
+        Ok(conn.last_insert_rowid())
+    }
+
+    /// Entries whose next attempt is due at the given unix timestamp.
+    pub fn get_due_outbox_entries(&self, now: i64) -> Result<Vec<OutboxEntry>> {
+        let conn = self.conn.lock().unwrap();
+
+        let mut stmt = conn.prepare(
+            "SELECT id, user_id, database_id, target, capture, attempts, last_error
+            FROM outbox
+            WHERE next_attempt_at <= ?1
+            ORDER BY id;",
+        )?;
+
+        let entries = stmt
+            .query_map(params![now], |row| {
+                Ok(OutboxEntry {
+                    id: row.get(0)?,
+                    user_id: row.get(1)?,
+                    target: target_from_row(row.get(3)?, row.get(2)?)?,
+                    capture: row.get(4)?,
+                    attempts: row.get(5)?,
+                    last_error: row.get(6)?,
+                })
+            })?
+            .collect::<Result<Vec<OutboxEntry>>>()?;
+
+        Ok(entries)
+    }
+
+    pub fn reschedule_outbox_entry(
+        &self,
+        id: i64,
+        next_attempt_at: i64,
+        error: &str,
+    ) -> Result<()> {
+        let conn = self.conn.lock().unwrap();
+
+        conn.execute(
+            "UPDATE outbox
+            SET attempts = attempts + 1, next_attempt_at = ?2, last_error = ?3
+            WHERE id = ?1;",
+            params![id, next_attempt_at, error],
+        )?;
+
+        Ok(())
+    }
+
+    pub fn delete_outbox_entry(&self, id: i64) -> Result<()> {
+        let conn = self.conn.lock().unwrap();
+
+        conn.execute(
+            "DELETE
+            FROM outbox
+            WHERE id = ?1;",
+            params![id],
+        )?;
+
+        Ok(())
+    }
+
+    pub fn add_tag_rule(
+        &self,
+        user_id: &str,
+        kind: RuleKind,
+        pattern: &str,
+        tag: &str,
+    ) -> Result<i64> {
+        let conn = self.conn.lock().unwrap();
+
+        conn.execute(
+            "INSERT INTO tag_rules (user_id, kind, pattern, tag)
+             VALUES (?,?,?,?)",
+            params![user_id, kind.as_str(), pattern, tag],
+        )?;
+
+        Ok(conn.last_insert_rowid())
+    }
+
+    pub fn get_tag_rules(&self, user_id: &str) -> Result<Vec<TagRule>> {
+        let conn = self.conn.lock().unwrap();
+
+        let mut stmt = conn.prepare(
+            "SELECT id, user_id, kind, pattern, tag
+            FROM tag_rules
+            WHERE user_id = ?1
+            ORDER BY id;",
+        )?;
+
+        let rules = stmt
+            .query_map(params![user_id], |row| {
+                let kind: String = row.get(2)?;
+
+                Ok(TagRule {
+                    id: row.get(0)?,
+                    user_id: row.get(1)?,
+                    kind: kind.parse().map_err(|_| {
+                        rusqlite::Error::InvalidColumnType(
+                            2,
+                            "kind".to_string(),
+                            rusqlite::types::Type::Text,
+                        )
+                    })?,
+                    pattern: row.get(3)?,
+                    tag: row.get(4)?,
+                })
+            })?
+            .collect::<Result<Vec<TagRule>>>()?;
+
+        Ok(rules)
+    }
+
+    /// Returns whether a rule was deleted.
+    pub fn delete_tag_rule(&self, user_id: &str, rule_id: i64) -> Result<bool> {
+        let conn = self.conn.lock().unwrap();
+
+        let deleted = conn.execute(
+            "DELETE
+            FROM tag_rules
+            WHERE user_id = ?1 AND id = ?2;",
+            params![user_id, rule_id],
+        )?;
+
+        Ok(deleted > 0)
+    }
 }
 
 fn get_db(path: &str) -> Result<Connection> {
@@ -95,9 +536,63 @@ fn run_migrations(conn: &Connection) -> Result<()> {
         [],
     )?;
 
+    // settings are stored as key value pairs, so new settings need no migration
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS user_settings (
+            user_id           TEXT NOT NULL,
+            key               TEXT NOT NULL,
+            value             TEXT NOT NULL,
+            PRIMARY KEY (user_id, key)
+        );",
+        [],
+    )?;
+
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS outbox (
+            id                INTEGER PRIMARY KEY AUTOINCREMENT,
+            user_id           TEXT NOT NULL,
+            database_id       TEXT NOT NULL,
+            capture           TEXT NOT NULL,
+            attempts          INTEGER NOT NULL,
+            next_attempt_at   INTEGER NOT NULL,
+            last_error        TEXT
+        );",
+        [],
+    )?;
+
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS tag_rules (
+            id                INTEGER PRIMARY KEY AUTOINCREMENT,
+            user_id           TEXT NOT NULL,
+            kind              TEXT NOT NULL,
+            pattern           TEXT NOT NULL,
+            tag               TEXT NOT NULL
+        );",
+        [],
+    )?;
+
+    // the target columns were added after the tables, so older databases are migrated
+    for table in ["user_details", "outbox"] {
+        if !has_column(conn, table, "target")? {
+            conn.execute(
+                &format!("ALTER TABLE {table} ADD COLUMN target TEXT NOT NULL DEFAULT 'database';"),
+                [],
+            )?;
+        }
+    }
+
     Ok(())
 }
 
+fn has_column(conn: &Connection, table: &str, column: &str) -> Result<bool> {
+    let mut stmt = conn.prepare(&format!("PRAGMA table_info({table});"))?;
+    let columns = stmt
+        .query_map([], |row| row.get::<_, String>(1))?
+        .collect::<Result<Vec<String>>>()?;
+
+    Ok(columns.iter().any(|name| name == column))
+}
+
 #[cfg(test)]
 mod tests {
     use std::{fs, path::Path};
@@ -120,16 +615,26 @@ mod tests {
         let user_details = UserDetails {
             user_id: "1".to_string(),
             integration_token: "2".to_string(),
-            database_id: "3".to_string(),
+            target: Target::Page("3".to_string()),
         };
 
         db.register(user_details.clone())?;
         let result = db.get("1")?;
         assert_eq!(Some(user_details), result);
 
+        let settings = UserSettings {
+            add_bookmark: false,
+            ..UserSettings::default()
+        };
+        db.save_settings("1", &settings)?;
+        db.add_to_outbox("1", &Target::Page("3".to_string()), "{}", 100, "")?;
+
+        // a user registering again starts over
         db.delete("1")?;
         let result = db.get("1")?;
         assert_eq!(None, result);
+        assert_eq!(UserSettings::default(), db.get_settings("1")?);
+        assert!(db.get_due_outbox_entries(100)?.is_empty());
 
         remove_db_if_exists(db_path);
 
@@ -151,6 +656,165 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn tag_rules() -> Result<()> {
+        let db_path = "test_db_4.sqlite";
+        remove_db_if_exists(db_path);
+
+        let db = Database::new(db_path)?;
+
+        let id = db.add_tag_rule("1", RuleKind::Domain, "github.com", "code")?;
+        db.add_tag_rule("2", RuleKind::Keyword, "recipe", "cooking")?;
+
+        let rules = db.get_tag_rules("1")?;
+        assert_eq!(
+            vec![TagRule {
+                id,
+                user_id: "1".to_string(),
+                kind: RuleKind::Domain,
+                pattern: "github.com".to_string(),
+                tag: "code".to_string(),
+            }],
+            rules
+        );
+
+        // rules of other users cannot be deleted
+        assert!(!db.delete_tag_rule("2", id)?);
+        assert!(db.delete_tag_rule("1", id)?);
+        assert!(db.get_tag_rules("1")?.is_empty());
+
+        // rules are deleted with the user
+        db.delete("2")?;
+        assert!(db.get_tag_rules("2")?.is_empty());
+
+        remove_db_if_exists(db_path);
+
+        Ok(())
+    }
+
+    #[test]
+    fn settings() -> Result<()> {
+        let db_path = "test_db_5.sqlite";
+        remove_db_if_exists(db_path);
+
+        let db = Database::new(db_path)?;
+
+        assert_eq!(UserSettings::default(), db.get_settings("1")?);
+
+        let settings = UserSettings {
+            timezone: Tz::Europe__Berlin,
+            date_property: Some("Due".to_string()),
+            add_bookmark: false,
+            reply_verbosity: ReplyVerbosity::Verbose,
+            ..UserSettings::default()
+        };
+        db.save_settings("1", &settings)?;
+        assert_eq!(settings, db.get_settings("1")?);
+
+        let settings = UserSettings {
+            date_property: None,
+            ..settings
+        };
+        db.save_settings("1", &settings)?;
+        assert_eq!(settings, db.get_settings("1")?);
+
+        remove_db_if_exists(db_path);
+
+        Ok(())
+    }
+
+    #[test]
+    fn outbox() -> Result<()> {
+        let db_path = "test_db_6.sqlite";
+        remove_db_if_exists(db_path);
+
+        let db = Database::new(db_path)?;
+
+        let id = db.add_to_outbox(
+            "1",
+            &Target::Database("2".to_string()),
+            "{}",
+            100,
+            "notion is unavailable",
+        )?;
+        assert!(db.get_due_outbox_entries(99)?.is_empty());
+
+        db.reschedule_outbox_entry(id, 200, "rate limited")?;
+        assert!(db.get_due_outbox_entries(199)?.is_empty());
+        assert_eq!(
+            vec![OutboxEntry {
+                id,
+                user_id: "1".to_string(),
+                target: Target::Database("2".to_string()),
+                capture: "{}".to_string(),
+                attempts: 1,
+                last_error: Some("rate limited".to_string()),
+            }],
+            db.get_due_outbox_entries(200)?
+        );
+
+        db.delete_outbox_entry(id)?;
+        assert!(db.get_due_outbox_entries(200)?.is_empty());
+
+        remove_db_if_exists(db_path);
+
+        Ok(())
+    }
+
+    #[test]
+    fn tables_without_target_are_migrated() -> Result<()> {
+        let db_path = "test_db_7.sqlite";
+        remove_db_if_exists(db_path);
+
+        // the tables as they were before targets
+        let conn = Connection::open(db_path)?;
+        conn.execute_batch(
+            "CREATE TABLE user_details (
+                user_id           TEXT NOT NULL,
+                integration_token TEXT NOT NULL,
+                database_id       TEXT NOT NULL,
+                PRIMARY KEY (user_id)
+            );
+            CREATE TABLE outbox (
+                id                INTEGER PRIMARY KEY AUTOINCREMENT,
+                user_id           TEXT NOT NULL,
+                database_id       TEXT NOT NULL,
+                capture           TEXT NOT NULL,
+                attempts          INTEGER NOT NULL,
+                next_attempt_at   INTEGER NOT NULL,
+                last_error        TEXT
+            );
+            INSERT INTO user_details VALUES ('1', '2', '3');
+            INSERT INTO outbox (user_id, database_id, capture, attempts, next_attempt_at)
+                VALUES ('1', '3', '{}', 0, 100);",
+        )?;
+        assert!(!has_column(&conn, "user_details", "target")?);
+        drop(conn);
+
+        let db = Database::new(db_path)?;
+
+        assert_eq!(
+            Some(UserDetails {
+                user_id: "1".to_string(),
+                integration_token: "2".to_string(),
+                target: Target::Database("3".to_string()),
+            }),
+            db.get("1")?
+        );
+        assert_eq!(
+            Target::Database("3".to_string()),
+            db.get_due_outbox_entries(100)?[0].target
+        );
+
+        // running the migrations again changes nothing
+        drop(db);
+        assert!(Database::new(db_path)?.get("1")?.is_some());
+
+        remove_db_if_exists(db_path);
+
+        Ok(())
+    }
+
     #[test]
     fn delete_non_existent() -> Result<()> {
         let db_path = "test_db_3.sqlite";
@@ -0,0 +1,107 @@
+use std::time::Duration;
+
+use thiserror::Error;
+
+pub type Result<T> = std::result::Result<T, BotError>;
+
+#[derive(Debug, Error)]
+pub enum BotError {
+    #[error("user is not registered")]
+    NotRegistered,
+    #[error("notion rejected the integration token")]
+    NotionAuth,
+    #[error("notion database is not shared with the integration")]
+    NotionNotShared,
+    #[error("database is missing properties, found: {found:?}")]
+    MissingProperties { found: Vec<String> },
+    #[error("notion validation failed: {0}")]
+    NotionValidation(String),
+    #[error("notion rate limit reached")]
+    NotionRateLimited {
+        /// how long notion asked to wait, if it told us
+        retry_after: Option<Duration>,
+    },
+    #[error("notion is unavailable: {0}")]
+    NotionUnavailable(String),
+    #[error("notion error: {0}")]
+    Notion(String),
+    #[error("image host is unavailable: {0}")]
+    ImageHostDown(String),
+    #[error("could not process image: {0}")]
+    ImageProcessing(String),
+    #[error("could not load web page: {0}")]
+    WebPage(String),
+    #[error("could not download file from telegram: {0}")]
+    TelegramDownload(String),
+    #[error("telegram request failed: {0}")]
+    Telegram(#[from] teloxide::RequestError),
+    #[error("database error: {0}")]
+    Database(#[from] rusqlite::Error),
+}
+
+impl BotError {
+    /// Whether the capture can be stored and tried again later.
+    pub fn is_retryable(&self) -> bool {
+        matches!(
+            self,
+            BotError::NotionRateLimited { .. }
+                | BotError::NotionUnavailable(_)
+                | BotError::ImageHostDown(_)
+        )
+    }
+
+    /// Maps the status of a failed notion request.
+    pub fn from_notion_status(status: u16, message: String) -> Self {
+        match status {
+            401 => BotError::NotionAuth,
+            403 | 404 => BotError::NotionNotShared,
+            400 | 409 => BotError::NotionValidation(message),
+            429 => BotError::NotionRateLimited { retry_after: None },
+            500..=599 => BotError::NotionUnavailable(message),
+            _ => BotError::Notion(message),
+        }
+    }
+
+    /// What the user is told when handling their message failed.
+    pub fn user_message(&self) -> String {
+        match self {
+            BotError::NotRegistered => {
+                "You are not set up yet, send me a message to start the setup.".to_string()
+            }
+            BotError::NotionAuth => "Notion did not accept your integration token. \
+                Check it at https://www.notion.so/my-integrations and send /reset to set it up again."
+                .to_string(),
+            BotError::NotionNotShared => "I cannot access your Notion database. \
+                Open the database in Notion, go to ••• → Connections and add your integration."
+                .to_string(),
+            BotError::MissingProperties { found } => format!(
+                "Your database does not have all required properties: Name, Image, URL, Tags. Found: {}",
+                found.join(", ")
+            ),
+            BotError::NotionValidation(message) => format!(
+                "Notion rejected the page: {message}\nCheck that the properties Name, URL, Tags and Image have the right types."
+            ),
+            BotError::NotionRateLimited { .. } => {
+                "Notion is receiving too many requests right now, please send this again in a minute."
+                    .to_string()
+            }
+            BotError::NotionUnavailable(_) | BotError::Notion(_) => {
+                "Notion is not reachable right now, please try again later.".to_string()
+            }
+            BotError::ImageHostDown(_) => {
+                "The image could not be uploaded because the image host is down, please try again later."
+                    .to_string()
+            }
+            BotError::ImageProcessing(_) => {
+                "I could not read this image, please send it as a photo instead.".to_string()
+            }
+            BotError::WebPage(_) => "I could not load the linked page.".to_string(),
+            BotError::TelegramDownload(_) => {
+                "I could not download your file from Telegram, please send it again.".to_string()
+            }
+            BotError::Telegram(_) | BotError::Database(_) => {
+                "Something went wrong on my side, please try again.".to_string()
+            }
+        }
+    }
+}
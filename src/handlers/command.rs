@@ -2,9 +2,18 @@ use std::{error::Error, sync::Arc};
 
 use teloxide::{prelude::*, utils::command::BotCommands};
 
-use crate::db::Database;
+use crate::{
+    constants::TEMPLATE_EXAMPLE_MSG,
+    db::{Database, Target},
+    notion::{NotionClients, PageTemplate},
+    tag_rules::parse_rule,
+};
 
-use super::dialogue::{SetupDialogue, State};
+use super::{
+    dialogue::{SetupDialogue, State},
+    recent::send_recent,
+    search::{send_search, SearchQueries},
+};
 
 #[derive(BotCommands, Clone)]
 #[command(
@@ -16,6 +25,33 @@ pub enum Command {
     Help,
     #[command(description = "resets the bot and deletes the user's tokens")]
     Reset,
+    #[command(description = "adds a tagging rule: /addrule <domain|keyword> <pattern> <tag>")]
+    AddRule(String),
+    #[command(description = "lists your tagging rules")]
+    Rules,
+    #[command(description = "deletes a tagging rule: /deleterule <id>")]
+    DeleteRule(String),
+    #[command(description = "reloads the properties of your notion database")]
+    Refresh,
+    #[command(description = "shows and changes your settings")]
+    Settings,
+    #[command(description = "sets your timezone, e.g. /timezone Europe/Berlin")]
+    Timezone(String),
+    #[command(
+        description = "sets the date property filled with dates like \"by friday\": /dateproperty <name>"
+    )]
+    DateProperty(String),
+    #[command(
+        description = "sets where messages are saved: /target <database|page|journal> <id or link>",
+        parse_with = "split"
+    )]
+    Target { kind: String, id: String },
+    #[command(description = "sets the template of new pages as json, /template clear removes it")]
+    Template(String),
+    #[command(description = "searches the titles and tags of your pages: /search <query>")]
+    Search(String),
+    #[command(description = "lists your latest pages: /recent [number]")]
+    Recent(String),
 }
 
 pub async fn handle_command(
@@ -24,6 +60,8 @@ pub async fn handle_command(
     cmd: Command,
     dialogue: SetupDialogue,
     db: Arc<Database>,
+    notion_clients: Arc<NotionClients>,
+    searches: Arc<SearchQueries>,
 ) -> Result<(), Box<dyn Error + Send + Sync>> {
     match cmd {
         Command::Help => {
@@ -32,6 +70,7 @@ pub async fn handle_command(
         }
         Command::Reset => {
             db.delete(&msg.chat.id.to_string())?;
+            notion_clients.evict(&msg.chat.id.to_string());
             bot.send_message(
                 msg.chat.id,
                 format!("Try again by sending a message to activate the setup"),
@@ -39,7 +78,226 @@ pub async fn handle_command(
             .await?;
             dialogue.update(State::Instructions).await?;
         }
+        Command::AddRule(rule) => {
+            let reply = match parse_rule(&rule) {
+                Ok((kind, pattern, tag)) => {
+                    let id = db.add_tag_rule(&msg.chat.id.to_string(), kind, &pattern, &tag)?;
+                    format!("Added rule {id}: {kind} \"{pattern}\" → {tag}")
+                }
+                Err(err) => format!("{err}, e.g. /addrule keyword recipe #[to cook]"),
+            };
+
+            bot.send_message(msg.chat.id, reply).await?;
+        }
+        Command::Rules => {
+            let rules = db.get_tag_rules(&msg.chat.id.to_string())?;
+
+            let reply = if rules.is_empty() {
+                "You have no tagging rules yet, add one with /addrule".to_string()
+            } else {
+                rules
+                    .iter()
+                    .map(|rule| rule.to_string())
+                    .collect::<Vec<String>>()
+                    .join("\n")
+            };
+
+            bot.send_message(msg.chat.id, reply).await?;
+        }
+        Command::DeleteRule(rule_id) => {
+            let reply = match rule_id.trim().parse::<i64>() {
+                Ok(rule_id) if db.delete_tag_rule(&msg.chat.id.to_string(), rule_id)? => {
+                    format!("Deleted rule {rule_id}")
+                }
+                Ok(rule_id) => format!("There is no rule with id {rule_id}"),
+                Err(_) => "Send the id of the rule, e.g. /deleterule 3, see /rules".to_string(),
+            };
+
+            bot.send_message(msg.chat.id, reply).await?;
+        }
+        Command::Refresh => {
+            let reply = match db.get(&msg.chat.id.to_string())? {
+                Some(user_details) => match &user_details.target {
+                    Target::Database(database_id) | Target::Journal(database_id) => {
+                        notion_clients
+                            .get(&user_details)?
+                            .invalidate_schema(database_id);
+                        "The properties of your database will be reloaded with your next message"
+                    }
+                    Target::Page(_) => "Messages are appended to a page, which has no properties",
+                },
+                None => "You are not set up yet, send me a message to start the setup",
+            };
+
+            bot.send_message(msg.chat.id, reply).await?;
+        }
+        // answered by `send_settings`, see `run_bot`
+        Command::Settings => {}
+        Command::Recent(count) => send_recent(bot, msg, count, db, notion_clients).await?,
+        Command::Search(query) => {
+            send_search(bot, msg, query, db, notion_clients, searches).await?
+        }
+        Command::Timezone(timezone) => {
+            let user_id = msg.chat.id.to_string();
+            let mut settings = db.get_settings(&user_id)?;
+
+            let reply = if timezone.trim().is_empty() {
+                format!("Your timezone is {}", settings.timezone)
+            } else {
+                match timezone.trim().parse() {
+                    Ok(timezone) => {
+                        settings.timezone = timezone;
+                        db.save_settings(&user_id, &settings)?;
+                        format!("Your timezone is now {timezone}")
+                    }
+                    Err(_) => {
+                        format!("Unknown timezone \"{timezone}\", use a name like Europe/Berlin")
+                    }
+                }
+            };
+
+            bot.send_message(msg.chat.id, reply).await?;
+        }
+        Command::DateProperty(date_property) => {
+            let user_id = msg.chat.id.to_string();
+            let mut settings = db.get_settings(&user_id)?;
+
+            let date_property = date_property.trim();
+            settings.date_property = if date_property.is_empty() {
+                None
+            } else {
+                Some(date_property.to_string())
+            };
+            db.save_settings(&user_id, &settings)?;
+
+            let reply = match &settings.date_property {
+                Some(date_property) => format!("Dates will be written to \"{date_property}\""),
+                None => "Dates will not be written to a property anymore".to_string(),
+            };
+
+            bot.send_message(msg.chat.id, reply).await?;
+        }
+        Command::Template(template) => {
+            let user_id = msg.chat.id.to_string();
+            let mut settings = db.get_settings(&user_id)?;
+
+            let reply = match template.trim() {
+                "" => match &settings.template {
+                    Some(template) => serde_json::to_string_pretty(template)?,
+                    None => TEMPLATE_EXAMPLE_MSG.to_string(),
+                },
+                "clear" => {
+                    settings.template = None;
+                    db.save_settings(&user_id, &settings)?;
+                    "New pages will not use a template anymore".to_string()
+                }
+                template => match serde_json::from_str::<PageTemplate>(template) {
+                    Ok(template) => {
+                        settings.template = Some(template);
+                        db.save_settings(&user_id, &settings)?;
+                        "New pages will use this template".to_string()
+                    }
+                    Err(err) => format!("This is not a valid template: {err}"),
+                },
+            };
+
+            bot.send_message(msg.chat.id, reply).await?;
+        }
+        Command::Target { kind, id } => {
+            let reply = match db.get(&msg.chat.id.to_string())? {
+                Some(mut user_details) => {
+                    let target = notion_id(&id)
+                        .ok_or(format!("{id} is not a notion id or link"))
+                        .and_then(|id| Target::from_parts(&kind.to_lowercase(), id));
+                    match target {
+                        Ok(target) => {
+                            user_details.target = target;
+                            db.register(user_details.clone())?;
+                            format!(
+                                "Messages are now saved to the {} {}",
+                                user_details.target.kind(),
+                                user_details.target.id()
+                            )
+                        }
+                        Err(err) => {
+                            format!("{err}, use /target <database|page|journal> <id or link>")
+                        }
+                    }
+                }
+                None => "You are not set up yet, send me a message to start the setup".to_string(),
+            };
+
+            bot.send_message(msg.chat.id, reply).await?;
+        }
     };
 
     Ok(())
 }
+
+/// The id of a page or database, also from a link to it like
+/// `https://www.notion.so/workspace/Reading-List-0123456789abcdef0123456789abcdef?v=…`.
+/// Ids are copied from notion with or without dashes.
+fn notion_id(input: &str) -> Option<String> {
+    let input = input.trim();
+    let last_segment = match url::Url::parse(input) {
+        Ok(url) => url
+            .path_segments()?
+            .rfind(|segment| !segment.is_empty())?
+            .to_string(),
+        Err(_) => input.to_string(),
+    };
+
+    // the title of the page comes before the id
+    let id = last_segment.replace('-', "");
+    let id = id.get(id.len().checked_sub(32)?..)?;
+
+    id.chars()
+        .all(|c| c.is_ascii_hexdigit())
+        .then(|| id.to_lowercase())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn finds_notion_ids() {
+        let id = "0123456789abcdef0123456789abcdef";
+
+        assert_eq!(Some(id.to_string()), notion_id(id));
+        assert_eq!(
+            Some(id.to_string()),
+            notion_id("01234567-89ab-cdef-0123-456789abcdef")
+        );
+        assert_eq!(
+            Some(id.to_string()),
+            notion_id(&format!("https://www.notion.so/workspace/Reading-List-{id}?v=fedcba9876543210fedcba9876543210"))
+        );
+        assert_eq!(
+            Some(id.to_string()),
+            notion_id(&format!("https://www.notion.so/{}", id.to_uppercase()))
+        );
+        assert_eq!(None, notion_id("https://www.notion.so/workspace"));
+        assert_eq!(None, notion_id("my-database"));
+    }
+
+    #[test]
+    fn parses_target_command() {
+        let command = Command::parse(
+            "/target page https://www.notion.so/Journal-0123456789abcdef0123456789abcdef",
+            "bot",
+        );
+
+        match command {
+            Ok(Command::Target { kind, id }) => {
+                assert_eq!("page", kind);
+                assert_eq!(
+                    Some("0123456789abcdef0123456789abcdef".to_string()),
+                    notion_id(&id)
+                );
+            }
+            _ => panic!("not parsed as target command"),
+        }
+        assert!(Command::parse("/target page", "bot").is_err());
+    }
+}
@@ -4,7 +4,7 @@ use teloxide::{dispatching::dialogue::InMemStorage, prelude::*, types::ParseMode
 
 use crate::{
     constants::INSTRUCTIONS_MSG,
-    db::{Database, UserDetails},
+    db::{Database, Target, UserDetails},
 };
 
 pub type SetupDialogue = Dialogue<State, InMemStorage<State>>;
@@ -110,7 +110,7 @@ pub async fn receive_confirm(
                 db.register(UserDetails {
                     user_id: msg.chat.id.to_string(),
                     integration_token,
-                    database_id,
+                    target: Target::Database(database_id),
                 })?;
 
                 // dialogue.exit().await?;
@@ -1,11 +1,82 @@
-use crate::db::Database;
-use crate::img_push::ImgPush;
-use crate::notion::{NewPage, Notion};
-use regex::Regex;
-use std::error::Error;
+use crate::db::{Database, ReplyVerbosity, Target};
+use crate::error::{BotError, Result};
+use crate::notion::{
+    find_property, parse_property_values, toggle_block, ContentBlock, NewEntry, NewPage, Notion,
+    NotionClients, PageFile, Placeholders,
+};
+use crate::outbox::OUTBOX_FIRST_RETRY_SECONDS;
+use crate::storage::{Image, ImageHost, ImageTarget};
+use crate::tag_rules::apply_tag_rules;
+use crate::text::{
+    find_date, handle_text, is_shortened, link_ranges, strip_tracking_parameters, TagOptions,
+};
+use crate::web::{
+    extract_article, fetch_file, fetch_html, fetch_post, fetch_video, format_duration,
+    resolve_redirects, snapshot, Article, SocialEndpoints, SocialPost, Video, VideoEndpoints,
+};
+use chrono::Utc;
+use rusticnotion::models::{properties::PropertyConfiguration, Database as NotionDatabase};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
 use std::sync::Arc;
+use teloxide::net::Download;
 use teloxide::prelude::*;
-use teloxide::types::{Document, PhotoSize};
+use teloxide::types::{Document, MessageId, PhotoSize};
+
+/// titles taken from the text in an image or a post are cut off
+const MAX_TITLE_LENGTH: usize = 100;
+/// larger images of posts stay linked on the platform
+const MAX_POST_IMAGE_SIZE: usize = 20 * 1024 * 1024;
+
+/// Everything needed to create a page from a message,
+/// so it can be stored and retried when notion or the image host is unavailable.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct Capture {
+    pub chat_id: i64,
+    pub message_id: i32,
+    pub text: String,
+    pub file_ids: Vec<String>,
+}
+
+impl Capture {
+    pub fn from_message(msg: &Message) -> Self {
+        let text = msg.text().unwrap_or("").to_string() + msg.caption().unwrap_or("");
+
+        Capture {
+            chat_id: msg.chat.id.0,
+            message_id: msg.id.0,
+            text,
+            file_ids: [get_image_id(msg.photo()), get_document_id(msg.document())]
+                .into_iter()
+                .flatten()
+                .collect(),
+        }
+    }
+}
+
+/// The reply to a saved capture, sent separately so failing to send it does not fail the capture.
+pub struct CaptureReply {
+    chat_id: ChatId,
+    message_id: MessageId,
+    /// empty when replies are turned off
+    text: String,
+    link_preview: bool,
+}
+
+impl CaptureReply {
+    pub async fn send(&self, bot: &Bot) -> Result<()> {
+        if self.text.is_empty() {
+            return Ok(());
+        }
+
+        bot.send_message(self.chat_id, &self.text)
+            .reply_to_message_id(self.message_id)
+            .disable_web_page_preview(!self.link_preview)
+            .await?;
+
+        Ok(())
+    }
+}
 
 fn get_image_id(image: Option<&[PhotoSize]>) -> Option<String> {
     let image = match image {
@@ -25,6 +96,16 @@ fn get_document_id(document: Option<&Document>) -> Option<String> {
             match (mime.type_(), mime.subtype()) {
                 (mime::IMAGE, mime::JPEG) => Some(document.file.id.to_owned()),
                 (mime::IMAGE, mime::PNG) => Some(document.file.id.to_owned()),
+                // re-encoded before uploading, see `ImageProcessing`
+                (mime::IMAGE, subtype) if subtype.as_str() == "webp" => {
+                    Some(document.file.id.to_owned())
+                }
+                // HEIC can only be decoded with libheif, it would be uploaded with its metadata
+                (mime::IMAGE, subtype)
+                    if cfg!(feature = "heic") && matches!(subtype.as_str(), "heic" | "heif") =>
+                {
+                    Some(document.file.id.to_owned())
+                }
                 _ => None,
             }
         } else {
@@ -35,45 +116,258 @@ fn get_document_id(document: Option<&Document>) -> Option<String> {
     }
 }
 
-async fn upload_image_file(bot: Bot, img_push: Arc<ImgPush>, file_id: String) -> Option<String> {
-    let file = bot.get_file(file_id.to_owned()).send().await;
+/// Downloads the file through the bot api, so the bot token is never handed to anyone else.
+async fn download_image_file(bot: &Bot, file_id: String) -> Result<Image> {
+    let file = bot
+        .get_file(file_id.to_owned())
+        .send()
+        .await
+        .map_err(|err| BotError::TelegramDownload(err.to_string()))?;
 
-    match file {
-        Ok(res) => {
-            let tg_url = format!(
-                "https://api.telegram.org/file/bot{}/{}",
-                bot.token(),
-                &res.path
-            );
-            let image_url = img_push.upload(&tg_url).await.unwrap();
+    let mut content = vec![];
+    bot.download_file(&file.path, &mut content)
+        .await
+        .map_err(|err| BotError::TelegramDownload(err.to_string()))?;
+
+    let filename = file.path.rsplit('/').next().unwrap_or(&file.path);
+    let extension = filename
+        .rsplit_once('.')
+        .map(|(_, extension)| extension.to_lowercase());
+    // telegram photos are always jpeg
+    let content_type = match extension.as_deref() {
+        Some("png") => "image/png",
+        Some("webp") => "image/webp",
+        Some("heic" | "heif") => "image/heic",
+        _ => "image/jpeg",
+    };
+
+    Ok(Image {
+        filename: filename.to_string(),
+        content_type: content_type.to_string(),
+        content,
+    })
+}
+
+async fn process_image(image_host: &ImageHost, image: Image) -> Result<Image> {
+    let processing = image_host.processing;
+
+    tokio::task::spawn_blocking(move || processing.process(image))
+        .await
+        .map_err(|err| BotError::ImageProcessing(err.to_string()))?
+}
 
-            Some(image_url)
+async fn upload_file(image_host: &ImageHost, notion: &Notion, image: Image) -> Result<PageFile> {
+    match &image_host.target {
+        ImageTarget::External(storage) => Ok(PageFile::External(storage.upload(&image).await?)),
+        ImageTarget::Notion => {
+            let upload_id = notion
+                .upload_file(&image.filename, &image.content_type, image.content)
+                .await?;
+            Ok(PageFile::FileUpload(upload_id))
         }
-        Err(_) => None,
     }
 }
 
+async fn load_article(url: &str) -> Result<Option<Article>> {
+    let html = fetch_html(url).await?;
+    let url = url.to_string();
+
+    // parsing large pages takes a while
+    tokio::task::spawn_blocking(move || extract_article(&html, &url))
+        .await
+        .map_err(|err| BotError::WebPage(err.to_string()))
+}
+
+/// Author and duration of the video as `key: value` pairs, like written in a message.
+fn video_properties(database: &NotionDatabase, video: &Video) -> Vec<(String, String)> {
+    let mut properties = vec![];
+
+    if let Some(author) = &video.author {
+        properties.push(("Author".to_string(), author.clone()));
+    }
+    if let Some(duration) = video.duration {
+        let duration = match find_property(database, "Duration") {
+            Some((_, PropertyConfiguration::Number { .. })) => duration.to_string(),
+            _ => format_duration(duration),
+        };
+        properties.push(("Duration".to_string(), duration));
+    }
+
+    properties
+}
+
+/// Stores an image of a post like a photo, so it is kept when the post is deleted.
+async fn store_post_image(image_host: &ImageHost, notion: &Notion, url: &str) -> Result<PageFile> {
+    let (content_type, content) = fetch_file(url, MAX_POST_IMAGE_SIZE).await?;
+    let content_type = content_type
+        .split(';')
+        .next()
+        .unwrap_or("")
+        .trim()
+        .to_lowercase();
+    if !content_type.starts_with("image/") {
+        return Err(BotError::WebPage(format!("not an image: {content_type}")));
+    }
+
+    let filename = url::Url::parse(url)
+        .ok()
+        .and_then(|url| url.path_segments()?.next_back().map(str::to_string))
+        .filter(|filename| !filename.is_empty())
+        .unwrap_or("image".to_string());
+    let image = process_image(
+        image_host,
+        Image {
+            filename,
+            content_type,
+            content,
+        },
+    )
+    .await?;
+
+    upload_file(image_host, notion, image).await
+}
+
+/// Stores a snapshot of the page with the configured storage.
+async fn archive_page(image_host: &ImageHost, notion: &Notion, url: &str) -> Result<PageFile> {
+    let html = snapshot(url).await?;
+    let host = url::Url::parse(url)
+        .ok()
+        .and_then(|url| url.host_str().map(str::to_string))
+        .unwrap_or("page".to_string());
+
+    let file = Image {
+        filename: format!("{host}-{}.html", Utc::now().format("%Y-%m-%d")),
+        content_type: mime::TEXT_HTML_UTF_8.to_string(),
+        content: html.into_bytes(),
+    };
+
+    upload_file(image_host, notion, file).await
+}
+
+/// What the link of a message adds to the page.
 #[derive(Default)]
-struct TextElements {
-    pub title: Option<String>,
-    pub url: Option<String>,
-    pub tags: Option<Vec<String>>,
+struct LinkContent {
+    title: Option<String>,
+    /// e.g. the thumbnail of a video, used when the message has no image
+    image: Option<PageFile>,
+    blocks: Vec<serde_json::Value>,
+    /// `key: value` pairs like written in a message
+    properties: Vec<(String, String)>,
+    /// embedded videos replace the bookmark
+    is_embedded: bool,
+}
+
+/// Downloads, processes and uploads the images of the message,
+/// and returns them with the text recognized in them.
+async fn capture_images(
+    bot: &Bot,
+    image_host: &ImageHost,
+    notion: &Notion,
+    file_ids: &[String],
+    ocr_enabled: bool,
+) -> Result<(Vec<PageFile>, Vec<String>)> {
+    let mut images = vec![];
+    let mut recognized_texts = vec![];
+    for id in file_ids {
+        let image = download_image_file(bot, id.to_owned()).await?;
+        let image = process_image(image_host, image).await?;
+
+        if let (Some(ocr), true) = (&image_host.ocr, ocr_enabled) {
+            // the page is still created without the text
+            match ocr.recognize(&image).await {
+                Ok(text) if !text.is_empty() => recognized_texts.push(text),
+                Ok(_) => {}
+                Err(err) => log::warn!("could not recognize text in image: {err}"),
+            }
+        }
+
+        images.push(upload_file(image_host, notion, image).await?);
+    }
+
+    Ok((images, recognized_texts))
 }
 
-fn handle_text(text: String) -> TextElements {
-    let title = text.lines().next().unwrap_or(&text).to_string();
+/// Embeds the video, quotes the post or extracts the article the link points to,
+/// the first that is found. The page is still created with the bookmark only otherwise.
+async fn link_content(
+    image_host: &ImageHost,
+    notion: &Notion,
+    url: &str,
+    database: Option<&NotionDatabase>,
+    extract_articles: bool,
+) -> LinkContent {
+    match fetch_video(url, &VideoEndpoints::default()).await {
+        Ok(Some(video)) => return video_content(url, &video, database),
+        Ok(None) => {}
+        Err(err) => log::warn!("could not load video details of {url}: {err}"),
+    }
 
-    let links_reg: Regex = Regex::new(r"(https?:\/\/[^\s]+)").unwrap();
-    let links = match_regex(links_reg, &text);
-    let first_link = links.and_then(|vec| vec.into_iter().next());
+    match fetch_post(url, &SocialEndpoints::default()).await {
+        Ok(Some(post)) => return post_content(image_host, notion, post).await,
+        Ok(None) => {}
+        Err(err) => log::warn!("could not load post {url}: {err}"),
+    }
 
-    let tags_reg: Regex = Regex::new(r"\s(?:@|#)(\w+)").unwrap();
-    let tags = match_regex(tags_reg, &text);
+    if !extract_articles {
+        return LinkContent::default();
+    }
+    match load_article(url).await {
+        Ok(Some(article)) => LinkContent {
+            title: article.title,
+            blocks: article.blocks.iter().map(ContentBlock::to_json).collect(),
+            ..LinkContent::default()
+        },
+        Ok(None) => {
+            log::info!("no article found at {url}");
+            LinkContent::default()
+        }
+        Err(err) => {
+            log::warn!("could not extract article from {url}: {err}");
+            LinkContent::default()
+        }
+    }
+}
 
-    TextElements {
-        title: Some(title),
-        url: first_link,
-        tags,
+fn video_content(url: &str, video: &Video, database: Option<&NotionDatabase>) -> LinkContent {
+    LinkContent {
+        title: Some(video.title.clone()),
+        image: video.thumbnail_url.clone().map(PageFile::External),
+        blocks: vec![ContentBlock::Video(url.to_string()).to_json()],
+        properties: database
+            .map(|database| video_properties(database, video))
+            .unwrap_or_default(),
+        is_embedded: true,
+    }
+}
+
+/// The post as quote, its images are stored like photos.
+async fn post_content(
+    image_host: &ImageHost,
+    notion: &Notion,
+    mut post: SocialPost,
+) -> LinkContent {
+    for image in &mut post.images {
+        let PageFile::External(url) = image else {
+            continue;
+        };
+        // the image stays linked on the platform
+        match store_post_image(image_host, notion, url).await {
+            Ok(stored) => *image = stored,
+            Err(err) => log::warn!("could not store image {url} of post: {err}"),
+        }
+    }
+
+    let title = match &post.title {
+        Some(title) => title.clone(),
+        None => post.text.lines().next().unwrap_or(&post.author).to_string(),
+    };
+
+    LinkContent {
+        title: Some(title.chars().take(MAX_TITLE_LENGTH).collect()),
+        image: None,
+        blocks: post.blocks().iter().map(ContentBlock::to_json).collect(),
+        properties: vec![("Author".to_string(), post.author.clone())],
+        is_embedded: false,
     }
 }
 
@@ -81,93 +375,351 @@ pub async fn message_handler(
     bot: Bot,
     msg: Message,
     db: Arc<Database>,
-    img_push: Arc<ImgPush>,
-) -> Result<(), Box<dyn Error + Send + Sync>> {
-    let user_details_query = db.get(&msg.chat.id.to_string())?;
-    let user_details = user_details_query.unwrap();
-
-    let notion = Notion::new(user_details.integration_token);
-    let database = match notion.get_database_by_id(user_details.database_id).await {
-        Ok(database) => database,
-        Err(err) => {
-            bot.send_message(
-                msg.chat.id,
-                format!(
-                    "An error occured, when accessing notion: {}",
-                    err.to_string()
-                ),
-            )
+    image_host: Arc<ImageHost>,
+    notion_clients: Arc<NotionClients>,
+) -> std::result::Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    let capture = Capture::from_message(&msg);
+    // the user may have been deleted by /reset since the message was routed here
+    let Some(user_details) = db.get(&msg.chat.id.to_string())? else {
+        bot.send_message(msg.chat.id, BotError::NotRegistered.user_message())
             .reply_to_message_id(msg.id)
             .await?;
+        return Ok(());
+    };
+
+    let result = add_capture_to_notion(
+        &bot,
+        &db,
+        &image_host,
+        &notion_clients,
+        &capture,
+        &user_details.target,
+        false,
+    )
+    .await;
+
+    match result {
+        Ok(reply) => {
+            if let Err(err) = reply.send(&bot).await {
+                log::error!("could not reply to message of chat {}: {err}", msg.chat.id);
+            }
+        }
+        Err(err) => {
+            log::error!(
+                "could not add message of chat {} to notion: {err}",
+                msg.chat.id
+            );
+
+            let reply = if err.is_retryable() {
+                let capture = serde_json::to_string(&capture)?;
+                db.add_to_outbox(
+                    &user_details.user_id,
+                    &user_details.target,
+                    &capture,
+                    Utc::now().timestamp() + OUTBOX_FIRST_RETRY_SECONDS,
+                    &err.to_string(),
+                )?;
 
-            return Err(err.to_string().into());
+                format!("Could not save this right now ({err}). I will retry and reply here once it is in Notion.")
+            } else {
+                err.user_message()
+            };
+
+            bot.send_message(msg.chat.id, reply)
+                .reply_to_message_id(msg.id)
+                .await?;
         }
+    }
+
+    Ok(())
+}
+
+/// Creates a page for the capture in the target database, or appends it to the target page
+/// or the journal page of the day, and returns the reply to the captured message.
+/// Retried captures are always answered, so the user knows the page was finally created.
+pub async fn add_capture_to_notion(
+    bot: &Bot,
+    db: &Database,
+    image_host: &ImageHost,
+    notion_clients: &NotionClients,
+    capture: &Capture,
+    target: &Target,
+    is_retry: bool,
+) -> Result<CaptureReply> {
+    let chat_id = ChatId(capture.chat_id);
+    let Some(user_details) = db.get(&chat_id.to_string())? else {
+        return Err(BotError::NotRegistered);
     };
+    let settings = db.get_settings(&user_details.user_id)?;
 
-    if !Notion::has_expected_database_properties(&database) {
-        let fields = database.properties.keys().collect::<Vec<&String>>();
-        let error_message = format!(
-            "Database does not have all required fields: Name, Image, URL, Tags, found: {fields:?}"
-        );
+    let notion = notion_clients.get(&user_details)?;
+    // pages have no properties, so there is nothing to check
+    let database = match target {
+        Target::Database(database_id) => {
+            let database = notion
+                .get_cached_database_by_id(database_id.to_string())
+                .await?;
 
-        bot.send_message(
-            msg.chat.id,
-            "Could not create page in Notion: ".to_string() + &error_message,
-        )
-        .await?;
+            if !Notion::has_expected_database_properties(&database) {
+                return Err(BotError::MissingProperties {
+                    found: database.properties.keys().cloned().collect(),
+                });
+            };
+            Some(database)
+        }
+        Target::Page(_) | Target::Journal(_) => None,
+    };
 
-        return Err(error_message.into());
+    let text = capture.text.clone();
+
+    let tag_options = TagOptions {
+        mentions_as_tags: settings.mentions_as_tags,
     };
+    let mut text_elements = handle_text(text.clone(), &tag_options, |key| {
+        database
+            .as_ref()
+            .is_some_and(|database| find_property(database, key).is_some())
+    });
 
-    let text = msg.text().unwrap_or("").to_string() + msg.caption().unwrap_or("");
+    if let Some(url) = text_elements.url.clone().filter(|url| is_shortened(url)) {
+        // the shortened link is saved if it can not be followed
+        match resolve_redirects(&url).await {
+            Ok(resolved) => {
+                let resolved = strip_tracking_parameters(&resolved);
+                if text_elements.title_is_missing() {
+                    text_elements.title = Some(resolved.clone());
+                }
+                text_elements.url = Some(resolved);
+            }
+            Err(err) => log::warn!("could not follow the redirects of {url}: {err}"),
+        }
+    }
 
-    let text_elements = handle_text(text);
-    let image_file_id = get_image_id(msg.photo());
-    let document_file_id = get_document_id(msg.document());
+    let tag_rules = db.get_tag_rules(&user_details.user_id)?;
+    text_elements.add_tags(apply_tag_rules(
+        &tag_rules,
+        &text,
+        text_elements.url.as_deref(),
+    ));
 
-    let mut urls = vec![];
-    if let Some(id) = image_file_id {
-        if let Some(url) = upload_image_file(bot.clone(), img_push.clone(), id).await {
-            urls.push(url);
+    // the images, the link and its snapshot are loaded from different places
+    let link = async {
+        match text_elements.url.as_deref() {
+            Some(url) => {
+                link_content(
+                    image_host,
+                    &notion,
+                    url,
+                    database.as_ref(),
+                    settings.extract_articles,
+                )
+                .await
+            }
+            None => LinkContent::default(),
         }
     };
-    if let Some(id) = document_file_id {
-        if let Some(url) = upload_image_file(bot.clone(), img_push.clone(), id).await {
-            urls.push(url);
+    let archive = async {
+        let url = text_elements
+            .url
+            .as_deref()
+            .filter(|_| settings.archive_pages)?;
+        // the page is still created without the archive
+        match archive_page(image_host, &notion, url).await {
+            Ok(archive) => Some(archive),
+            Err(err) => {
+                log::warn!("could not archive {url}: {err}");
+                None
+            }
         }
     };
+    let (images, link, archive) = tokio::join!(
+        capture_images(bot, image_host, &notion, &capture.file_ids, settings.ocr),
+        link,
+        archive
+    );
+    let (images, recognized_texts) = images?;
 
-    let image_url = urls.iter().next();
+    let mut image = images.into_iter().next();
 
-    let new_page = NewPage {
-        database,
-        name: text_elements.title,
-        tags: text_elements.tags,
-        url: text_elements.url,
-        image_url: image_url.cloned(),
+    let recognized_text = recognized_texts.join("\n\n");
+    if text_elements
+        .title
+        .as_deref()
+        .unwrap_or("")
+        .trim()
+        .is_empty()
+    {
+        if let Some(first_line) = recognized_text.lines().next() {
+            text_elements.title = Some(first_line.chars().take(MAX_TITLE_LENGTH).collect());
+        }
+    }
+    let mut extra_blocks = if recognized_text.is_empty() {
+        vec![]
+    } else {
+        vec![toggle_block("Text in image", &recognized_text)]
     };
-    let page = notion.create_page(new_page).await.unwrap();
-    let page_id = page.id.to_string().replace("-", "");
 
-    bot.send_message(
-        msg.chat.id,
-        format!("Created page https://notion.so/{page_id}"),
-    )
-    .reply_to_message_id(msg.id)
-    .await?;
+    if let (Some(title), true) = (link.title, text_elements.title_is_missing()) {
+        text_elements.title = Some(title);
+    }
+    if image.is_none() {
+        image = link.image;
+    }
+    extra_blocks.extend(link.blocks);
+    // an embedded video replaces the bookmark
+    let add_bookmark = settings.add_bookmark && !link.is_embedded;
 
-    Ok(())
-}
+    // values written in the message win over the ones of the video or post
+    let mut raw_properties = link.properties;
+    raw_properties.extend(text_elements.properties.iter().cloned());
 
-fn match_regex(reg: Regex, text: &String) -> Option<Vec<String>> {
-    let tags: Vec<String> = reg
-        .captures_iter(&text)
-        .map(|cap| cap.get(1).unwrap().as_str().to_string())
-        .collect();
+    let today = settings.today();
+    let (properties, mut property_errors) = match &database {
+        Some(database) => parse_property_values(database, &raw_properties, today),
+        None => (HashMap::new(), vec![]),
+    };
 
-    if tags.len() != 0 {
-        Some(tags)
-    } else {
-        None
+    // only written to a property of the database
+    let date = database
+        .as_ref()
+        .and_then(|_| find_date(&text, today, &link_ranges(&text)));
+
+    let mut default_properties = HashMap::new();
+    let mut template_blocks = vec![];
+    if let (Some(template), Some(database)) = (&settings.template, &database) {
+        let placeholders = Placeholders {
+            title: text_elements.title.clone().unwrap_or_default(),
+            url: text_elements.url.clone().unwrap_or_default(),
+            date: date.unwrap_or(today).to_string(),
+            tags: text_elements.tags.clone().unwrap_or_default().join(", "),
+        };
+
+        let (properties, errors) =
+            parse_property_values(database, &template.properties(&placeholders), today);
+        default_properties = properties;
+        property_errors.extend(errors);
+        template_blocks = template
+            .blocks(&placeholders)
+            .iter()
+            .map(ContentBlock::to_json)
+            .collect();
+    }
+
+    let mut summary = vec![];
+    if let Some(title) = &text_elements.title {
+        summary.push(format!("Title: {title}"));
     }
+    if let Some(tags) = &text_elements.tags {
+        summary.push(format!("Tags: {}", tags.join(", ")));
+    }
+    if let (Some(date), Some(date_property)) = (date, &settings.date_property) {
+        summary.push(format!("{date_property}: {date}"));
+    }
+    summary.extend(properties.keys().map(|name| format!("Set {name}")));
+
+    // content that is missing on the created page or entry
+    let mut warnings = vec![];
+    let created = match database {
+        Some(database) => {
+            let new_page = NewPage {
+                database,
+                name: text_elements.title,
+                tags: text_elements.tags,
+                url: text_elements.url,
+                image,
+                archive,
+                properties,
+                date,
+                date_property: settings.date_property,
+                full_text: settings.include_full_text.then(|| text.clone()),
+                add_bookmark,
+                create_unknown_tags: settings.create_unknown_tags,
+                extra_blocks,
+                default_properties,
+                template_blocks,
+            };
+            let page = match notion.create_page(new_page).await {
+                Ok(created) => {
+                    warnings = created.warnings;
+                    created.page
+                }
+                Err(err) => {
+                    if let BotError::NotionValidation(_) = err {
+                        // the database might have changed since its schema was cached
+                        notion.invalidate_schema(target.id());
+                    }
+                    return Err(err);
+                }
+            };
+            let page_id = page.id.to_string().replace("-", "");
+
+            format!("Created page https://notion.so/{page_id}")
+        }
+        None => {
+            let now = Utc::now().with_timezone(&settings.timezone);
+            let (page_id, timestamp) = match target {
+                // the journal page is named after the day already
+                Target::Journal(database_id) => (
+                    notion.journal_page(database_id, now.date_naive()).await?,
+                    now.format("%H:%M").to_string(),
+                ),
+                _ => (
+                    target.id().to_string(),
+                    now.format("%Y-%m-%d %H:%M").to_string(),
+                ),
+            };
+
+            let new_entry = NewEntry {
+                timestamp,
+                title: text_elements.title,
+                url: text_elements.url,
+                image,
+                archive,
+                tags: text_elements.tags,
+                text: text.clone(),
+                add_bookmark,
+                extra_blocks,
+            };
+            let heading_id = match notion.append_entry(&page_id, new_entry).await {
+                Ok(appended) => {
+                    warnings = appended.warnings;
+                    appended.heading_id
+                }
+                Err(err) => {
+                    if let (
+                        Target::Journal(database_id),
+                        BotError::NotionNotShared | BotError::NotionValidation(_),
+                    ) = (target, &err)
+                    {
+                        // the page of the day might have been deleted, it is looked up again
+                        notion.forget_journal_page(database_id, now.date_naive());
+                    }
+                    return Err(err);
+                }
+            };
+
+            format!(
+                "Added to page https://notion.so/{}#{}",
+                page_id.replace("-", ""),
+                heading_id.replace("-", "")
+            )
+        }
+    };
+
+    let mut reply = match settings.reply_verbosity {
+        ReplyVerbosity::Quiet if !is_retry => vec![],
+        ReplyVerbosity::Quiet => vec![created],
+        ReplyVerbosity::Normal => vec![created],
+        ReplyVerbosity::Verbose => [created].into_iter().chain(summary).collect(),
+    };
+    // errors are reported even when replies are turned off
+    reply.extend(property_errors.iter().map(|error| error.to_string()));
+    reply.extend(warnings);
+
+    Ok(CaptureReply {
+        chat_id,
+        message_id: MessageId(capture.message_id),
+        text: reply.join("\n"),
+        link_preview: settings.link_preview,
+    })
 }
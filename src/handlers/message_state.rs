@@ -0,0 +1,51 @@
+use std::{
+    collections::HashMap,
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+
+use teloxide::types::{ChatId, MessageId};
+
+/// buttons and replies of older messages stop working
+const MESSAGE_STATE_EXPIRY: Duration = Duration::from_secs(24 * 60 * 60);
+
+/// Values kept for sent messages, e.g. the query of search results,
+/// as callback data is too short to hold them.
+pub struct MessageState<T> {
+    values: Mutex<HashMap<(ChatId, MessageId), (T, Instant)>>,
+}
+
+impl<T> Default for MessageState<T> {
+    fn default() -> Self {
+        Self {
+            values: Mutex::new(HashMap::new()),
+        }
+    }
+}
+
+impl<T: Clone> MessageState<T> {
+    pub fn insert(&self, chat_id: ChatId, message_id: MessageId, value: T) {
+        let mut values = self.values.lock().unwrap();
+
+        values.retain(|_, (_, created_at)| created_at.elapsed() < MESSAGE_STATE_EXPIRY);
+        values.insert((chat_id, message_id), (value, Instant::now()));
+    }
+
+    pub fn get(&self, chat_id: ChatId, message_id: MessageId) -> Option<T> {
+        let values = self.values.lock().unwrap();
+
+        values
+            .get(&(chat_id, message_id))
+            .filter(|(_, created_at)| created_at.elapsed() < MESSAGE_STATE_EXPIRY)
+            .map(|(value, _)| value.clone())
+    }
+
+    pub fn remove(&self, chat_id: ChatId, message_id: MessageId) -> Option<T> {
+        let mut values = self.values.lock().unwrap();
+
+        values
+            .remove(&(chat_id, message_id))
+            .filter(|(_, created_at)| created_at.elapsed() < MESSAGE_STATE_EXPIRY)
+            .map(|(value, _)| value)
+    }
+}
@@ -1,3 +1,7 @@
 pub mod command;
 pub mod dialogue;
-pub mod message;
\ No newline at end of file
+pub mod message;
+pub mod message_state;
+pub mod recent;
+pub mod search;
+pub mod settings;
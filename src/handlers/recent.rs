@@ -0,0 +1,473 @@
+use std::sync::Arc;
+
+use teloxide::{
+    prelude::*,
+    types::{ForceReply, InlineKeyboardButton, InlineKeyboardButtonKind, InlineKeyboardMarkup},
+};
+
+use super::message_state::MessageState;
+use crate::{
+    db::Database,
+    error::{BotError, Result},
+    notion::{recent_query, FoundPage, NotionClients},
+    text::{handle_text, TagOptions},
+};
+
+type HandlerResult = std::result::Result<(), Box<dyn std::error::Error + Send + Sync>>;
+
+pub const RECENT_CALLBACK_PREFIX: &str = "recent:";
+const DEFAULT_RECENT_PAGES: u32 = 5;
+/// every page has a row of buttons, so the list stays short
+const MAX_RECENT_PAGES: u32 = 10;
+
+/// The page whose tags are replaced by the reply to the prompt.
+#[derive(Clone, Debug, PartialEq)]
+pub struct RetagPrompt {
+    page_id: String,
+}
+
+pub type RetagPrompts = MessageState<RetagPrompt>;
+
+fn recent_message(pages: &[FoundPage]) -> (String, InlineKeyboardMarkup) {
+    let lines: Vec<String> = pages
+        .iter()
+        .enumerate()
+        .map(|(index, page)| {
+            let title = if page.title.is_empty() {
+                "Untitled"
+            } else {
+                &page.title
+            };
+            let mut line = format!("{}. {title}", index + 1);
+            if !page.tags.is_empty() {
+                line.push_str(&format!("\nTags: {}", page.tags.join(", ")));
+            }
+            line.push_str(&format!("\n{}", page.url));
+            line
+        })
+        .collect();
+
+    let buttons = pages
+        .iter()
+        .enumerate()
+        .map(|(index, page)| {
+            let number = (index + 1).to_string();
+
+            let mut row = vec![];
+            if let Ok(url) = page.url.parse() {
+                row.push(InlineKeyboardButton::url(format!("{number} Open"), url));
+            }
+            row.extend(page_buttons(&number, &page.id, false));
+            row
+        })
+        .collect::<Vec<_>>();
+
+    (lines.join("\n\n"), InlineKeyboardMarkup::new(buttons))
+}
+
+/// The buttons acting on a page, archiving asks for confirmation first.
+fn page_buttons(number: &str, page_id: &str, confirming: bool) -> Vec<InlineKeyboardButton> {
+    let callback = |label: &str, action: &str| {
+        InlineKeyboardButton::callback(
+            format!("{number} {label}"),
+            format!("{RECENT_CALLBACK_PREFIX}{action}:{page_id}"),
+        )
+    };
+
+    if confirming {
+        vec![
+            callback("Archive now", "confirm"),
+            callback("Cancel", "cancel"),
+        ]
+    } else {
+        vec![callback("Archive", "archive"), callback("Tags", "tags")]
+    }
+}
+
+/// Replaces the buttons of the page, `None` removes its row, e.g. once it is archived.
+fn replace_page_buttons(
+    keyboard: &InlineKeyboardMarkup,
+    page_id: &str,
+    confirming: Option<bool>,
+) -> InlineKeyboardMarkup {
+    let rows = keyboard
+        .inline_keyboard
+        .iter()
+        .filter_map(|row| {
+            let is_page_row = row.iter().any(|button| match &button.kind {
+                InlineKeyboardButtonKind::CallbackData(data) => {
+                    parse_callback(data).is_some_and(|(_, id)| id == page_id)
+                }
+                _ => false,
+            });
+            if !is_page_row {
+                return Some(row.clone());
+            }
+
+            let number = row.first()?.text.split_whitespace().next()?;
+            let mut row: Vec<InlineKeyboardButton> = row
+                .iter()
+                .filter(|button| !matches!(button.kind, InlineKeyboardButtonKind::CallbackData(_)))
+                .cloned()
+                .collect();
+            row.extend(page_buttons(number, page_id, confirming?));
+            Some(row)
+        })
+        .collect::<Vec<_>>();
+
+    InlineKeyboardMarkup::new(rows)
+}
+
+/// The action and the page id of a button, e.g. `recent:archive:<page id>`.
+fn parse_callback(data: &str) -> Option<(&str, &str)> {
+    data.strip_prefix(RECENT_CALLBACK_PREFIX)?.split_once(':')
+}
+
+async fn recent_pages(
+    db: &Database,
+    notion_clients: &NotionClients,
+    chat_id: ChatId,
+    count: u32,
+) -> Result<Option<Vec<FoundPage>>> {
+    let Some(user_details) = db.get(&chat_id.to_string())? else {
+        return Err(BotError::NotRegistered);
+    };
+    let Some(database_id) = user_details.target.database_id() else {
+        return Ok(None);
+    };
+
+    let notion = notion_clients.get(&user_details)?;
+    let results = notion
+        .query_database(database_id, recent_query(), count, None)
+        .await?;
+
+    Ok(Some(results.pages))
+}
+
+pub async fn send_recent(
+    bot: Bot,
+    msg: Message,
+    count: String,
+    db: Arc<Database>,
+    notion_clients: Arc<NotionClients>,
+) -> HandlerResult {
+    let count = match count.trim() {
+        "" => DEFAULT_RECENT_PAGES,
+        count => match count.parse::<u32>() {
+            Ok(count) => count.clamp(1, MAX_RECENT_PAGES),
+            Err(_) => {
+                bot.send_message(msg.chat.id, "Send the number of pages, e.g. /recent 5")
+                    .await?;
+                return Ok(());
+            }
+        },
+    };
+
+    let result = recent_pages(&db, &notion_clients, msg.chat.id, count).await;
+    let reply = match result {
+        Ok(Some(pages)) if !pages.is_empty() => {
+            let (text, keyboard) = recent_message(&pages);
+            bot.send_message(msg.chat.id, text)
+                .disable_web_page_preview(true)
+                .reply_markup(keyboard)
+        }
+        Ok(Some(_)) => bot.send_message(msg.chat.id, "There are no pages yet"),
+        Ok(None) => bot.send_message(
+            msg.chat.id,
+            "Messages are appended to a page, which has no list of pages",
+        ),
+        Err(err) => {
+            log::error!("could not list recent pages of chat {}: {err}", msg.chat.id);
+            bot.send_message(msg.chat.id, err.user_message())
+        }
+    };
+    reply.await?;
+
+    Ok(())
+}
+
+/// Archives a page once confirmed or asks for its new tags.
+pub async fn recent_callback(
+    bot: Bot,
+    q: CallbackQuery,
+    db: Arc<Database>,
+    notion_clients: Arc<NotionClients>,
+    retag_prompts: Arc<RetagPrompts>,
+) -> HandlerResult {
+    let (Some((action, page_id)), Some(message)) =
+        (q.data.as_deref().and_then(parse_callback), q.message)
+    else {
+        return Ok(());
+    };
+
+    match action {
+        // a single tap would archive the wrong page too easily
+        "archive" | "cancel" => {
+            bot.answer_callback_query(q.id).await?;
+
+            if let Some(keyboard) = message.reply_markup() {
+                bot.edit_message_reply_markup(message.chat.id, message.id)
+                    .reply_markup(replace_page_buttons(
+                        keyboard,
+                        page_id,
+                        Some(action == "archive"),
+                    ))
+                    .await?;
+            }
+        }
+        "confirm" => {
+            let result = match db.get(&message.chat.id.to_string())? {
+                Some(user_details) => match notion_clients.get(&user_details) {
+                    Ok(notion) => notion.archive_page(page_id).await,
+                    Err(err) => Err(err),
+                },
+                None => Err(BotError::NotRegistered),
+            };
+
+            if let Err(err) = result {
+                log::error!("could not archive page {page_id}: {err}");
+                bot.answer_callback_query(q.id)
+                    .text(err.user_message())
+                    .show_alert(true)
+                    .await?;
+                return Ok(());
+            }
+
+            bot.answer_callback_query(q.id).text("Archived").await?;
+
+            // the buttons of the archived page are removed
+            if let Some(keyboard) = message.reply_markup() {
+                bot.edit_message_reply_markup(message.chat.id, message.id)
+                    .reply_markup(replace_page_buttons(keyboard, page_id, None))
+                    .await?;
+            }
+        }
+        "tags" => {
+            bot.answer_callback_query(q.id).await?;
+
+            let prompt = bot
+                .send_message(
+                    message.chat.id,
+                    "Reply with the new tags, e.g. #read #[long read], or - to remove all tags",
+                )
+                .reply_to_message_id(message.id)
+                .reply_markup(ForceReply::new())
+                .await?;
+
+            retag_prompts.insert(
+                message.chat.id,
+                prompt.id,
+                RetagPrompt {
+                    page_id: page_id.to_string(),
+                },
+            );
+        }
+        _ => {
+            bot.answer_callback_query(q.id).await?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Whether the message answers a prompt for new tags.
+pub fn is_retag_reply(msg: &Message, retag_prompts: &RetagPrompts) -> bool {
+    msg.reply_to_message()
+        .is_some_and(|prompt| retag_prompts.get(msg.chat.id, prompt.id).is_some())
+}
+
+pub async fn receive_tags(
+    bot: Bot,
+    msg: Message,
+    db: Arc<Database>,
+    notion_clients: Arc<NotionClients>,
+    retag_prompts: Arc<RetagPrompts>,
+) -> HandlerResult {
+    let Some(RetagPrompt { page_id }) = msg
+        .reply_to_message()
+        .and_then(|prompt| retag_prompts.remove(msg.chat.id, prompt.id))
+    else {
+        return Ok(());
+    };
+
+    let text = msg.text().unwrap_or("").trim();
+    let tags = if text == "-" {
+        vec![]
+    } else {
+        let settings = db.get_settings(&msg.chat.id.to_string())?;
+        let tag_options = TagOptions {
+            mentions_as_tags: settings.mentions_as_tags,
+        };
+
+        // tags can also be sent without #, separated by commas
+        handle_text(text.to_string(), &tag_options, |_| false)
+            .tags
+            .unwrap_or_else(|| {
+                text.split(',')
+                    .map(str::trim)
+                    .filter(|tag| !tag.is_empty())
+                    .map(str::to_string)
+                    .collect()
+            })
+    };
+
+    let result = set_tags(&db, &notion_clients, msg.chat.id, &page_id, &tags).await;
+    let reply = match result {
+        Ok(None) => "Messages are appended to a page, which has no tags".to_string(),
+        Ok(Some(unknown_tags)) => tags_reply(&tags, &unknown_tags),
+        Err(err) => {
+            log::error!("could not set tags of page {page_id}: {err}");
+            err.user_message()
+        }
+    };
+
+    bot.send_message(msg.chat.id, reply)
+        .reply_to_message_id(msg.id)
+        .await?;
+
+    Ok(())
+}
+
+/// What was set, tags that are not in the database are only set if unknown tags are created.
+fn tags_reply(tags: &[String], unknown_tags: &[String]) -> String {
+    let set_tags: Vec<&str> = tags
+        .iter()
+        .filter(|tag| !unknown_tags.contains(tag))
+        .map(String::as_str)
+        .collect();
+    let hint = "Turn on creating new tags in /settings to add them.";
+
+    match (set_tags.is_empty(), unknown_tags.is_empty()) {
+        (true, true) => "Removed all tags".to_string(),
+        (false, true) => format!("Tags set to {}", set_tags.join(", ")),
+        (true, false) => format!(
+            "The tags were not changed, your database has no tags {}. {hint}",
+            unknown_tags.join(", ")
+        ),
+        (false, false) => format!(
+            "Tags set to {}, left out {} as your database does not have them. {hint}",
+            set_tags.join(", "),
+            unknown_tags.join(", ")
+        ),
+    }
+}
+
+/// The tags that were left out, see `Notion::set_tags`,
+/// `None` if the user appends to a page, the tags are not set then.
+async fn set_tags(
+    db: &Database,
+    notion_clients: &NotionClients,
+    chat_id: ChatId,
+    page_id: &str,
+    tags: &[String],
+) -> Result<Option<Vec<String>>> {
+    let Some(user_details) = db.get(&chat_id.to_string())? else {
+        return Err(BotError::NotRegistered);
+    };
+    let Some(database_id) = user_details.target.database_id() else {
+        return Ok(None);
+    };
+    let settings = db.get_settings(&user_details.user_id)?;
+
+    let notion = notion_clients.get(&user_details)?;
+    let database = notion
+        .get_cached_database_by_id(database_id.to_string())
+        .await?;
+
+    let unknown_tags = notion
+        .set_tags(page_id, &database, tags, settings.create_unknown_tags)
+        .await?;
+
+    Ok(Some(unknown_tags))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn pages() -> Vec<FoundPage> {
+        vec![
+            FoundPage {
+                id: "1".to_string(),
+                title: "Rust".to_string(),
+                url: "https://www.notion.so/1".to_string(),
+                tags: vec!["code".to_string(), "read".to_string()],
+            },
+            FoundPage {
+                id: "2".to_string(),
+                title: String::new(),
+                url: "https://www.notion.so/2".to_string(),
+                tags: vec![],
+            },
+        ]
+    }
+
+    fn labels(keyboard: &InlineKeyboardMarkup) -> Vec<Vec<&str>> {
+        keyboard
+            .inline_keyboard
+            .iter()
+            .map(|row| row.iter().map(|button| button.text.as_str()).collect())
+            .collect()
+    }
+
+    #[test]
+    fn lists_pages_with_buttons() {
+        let (text, keyboard) = recent_message(&pages());
+
+        assert_eq!(
+            "1. Rust\nTags: code, read\nhttps://www.notion.so/1\n\n2. Untitled\nhttps://www.notion.so/2",
+            text
+        );
+        assert_eq!(
+            vec![
+                vec!["1 Open", "1 Archive", "1 Tags"],
+                vec!["2 Open", "2 Archive", "2 Tags"]
+            ],
+            labels(&keyboard)
+        );
+    }
+
+    #[test]
+    fn archiving_is_confirmed() {
+        let (_, keyboard) = recent_message(&pages());
+
+        let confirming = replace_page_buttons(&keyboard, "2", Some(true));
+        assert_eq!(
+            vec![
+                vec!["1 Open", "1 Archive", "1 Tags"],
+                vec!["2 Open", "2 Archive now", "2 Cancel"]
+            ],
+            labels(&confirming)
+        );
+        assert_eq!(
+            keyboard,
+            replace_page_buttons(&confirming, "2", Some(false))
+        );
+        assert_eq!(
+            vec![vec!["1 Open", "1 Archive", "1 Tags"]],
+            labels(&replace_page_buttons(&confirming, "2", None))
+        );
+    }
+
+    #[test]
+    fn parses_callbacks() {
+        assert_eq!(
+            Some(("archive", "0123-abcd")),
+            parse_callback("recent:archive:0123-abcd")
+        );
+        assert_eq!(None, parse_callback("search:next:0123"));
+        assert_eq!(None, parse_callback("recent:archive"));
+    }
+
+    #[test]
+    fn reports_left_out_tags() {
+        let tags = |tags: &[&str]| tags.iter().map(|tag| tag.to_string()).collect::<Vec<_>>();
+
+        assert_eq!("Removed all tags", tags_reply(&[], &[]));
+        assert_eq!("Tags set to read", tags_reply(&tags(&["read"]), &[]));
+        assert!(tags_reply(&tags(&["later"]), &tags(&["later"]))
+            .starts_with("The tags were not changed"));
+        assert!(tags_reply(&tags(&["read", "later"]), &tags(&["later"]))
+            .starts_with("Tags set to read, left out later"));
+    }
+}
@@ -0,0 +1,169 @@
+use std::sync::Arc;
+
+use teloxide::{
+    prelude::*,
+    types::{InlineKeyboardButton, InlineKeyboardMarkup},
+};
+
+use super::message_state::MessageState;
+use crate::{
+    db::Database,
+    error::{BotError, Result},
+    notion::{search_query, NotionClients},
+};
+
+type HandlerResult = std::result::Result<(), Box<dyn std::error::Error + Send + Sync>>;
+
+pub const SEARCH_CALLBACK_PREFIX: &str = "search:";
+const SEARCH_PAGE_SIZE: u32 = 5;
+
+/// The query of sent search results, for the "more" button.
+#[derive(Clone, Debug, PartialEq)]
+pub struct SearchQuery(String);
+
+pub type SearchQueries = MessageState<SearchQuery>;
+
+/// The results as message text, with a button for the next results if there are more.
+async fn search(
+    db: &Database,
+    notion_clients: &NotionClients,
+    chat_id: ChatId,
+    query: &str,
+    cursor: Option<&str>,
+) -> Result<(String, Option<InlineKeyboardMarkup>)> {
+    let Some(user_details) = db.get(&chat_id.to_string())? else {
+        return Err(BotError::NotRegistered);
+    };
+    let Some(database_id) = user_details.target.database_id() else {
+        return Ok((
+            "Messages are appended to a page, search works with a database only".to_string(),
+            None,
+        ));
+    };
+
+    let notion = notion_clients.get(&user_details)?;
+    let database = notion
+        .get_cached_database_by_id(database_id.to_string())
+        .await?;
+    let results = notion
+        .query_database(
+            database_id,
+            search_query(&database, query),
+            SEARCH_PAGE_SIZE,
+            cursor,
+        )
+        .await?;
+
+    if results.pages.is_empty() {
+        let text = match cursor {
+            Some(_) => format!("No more pages found for \"{query}\""),
+            None => format!("No pages found for \"{query}\""),
+        };
+        return Ok((text, None));
+    }
+
+    let mut lines = vec![format!("Pages for \"{query}\":")];
+    lines.extend(results.pages.iter().map(|page| {
+        let title = if page.title.is_empty() {
+            "Untitled"
+        } else {
+            &page.title
+        };
+        format!("• {title}\n{}", page.url)
+    }));
+
+    let keyboard = results.next_cursor.map(|cursor| {
+        InlineKeyboardMarkup::new(vec![vec![InlineKeyboardButton::callback(
+            "More",
+            format!("{SEARCH_CALLBACK_PREFIX}{cursor}"),
+        )]])
+    });
+
+    Ok((lines.join("\n\n"), keyboard))
+}
+
+pub async fn send_search(
+    bot: Bot,
+    msg: Message,
+    query: String,
+    db: Arc<Database>,
+    notion_clients: Arc<NotionClients>,
+    searches: Arc<SearchQueries>,
+) -> HandlerResult {
+    let query = query.trim();
+    if query.is_empty() {
+        bot.send_message(msg.chat.id, "Tell me what to search for, e.g. /search rust")
+            .await?;
+        return Ok(());
+    }
+
+    let (text, keyboard) = match search(&db, &notion_clients, msg.chat.id, query, None).await {
+        Ok(results) => results,
+        Err(err) => {
+            log::error!("could not search for chat {}: {err}", msg.chat.id);
+            (err.user_message(), None)
+        }
+    };
+
+    let mut reply = bot
+        .send_message(msg.chat.id, text)
+        .disable_web_page_preview(true);
+    if let Some(keyboard) = keyboard {
+        reply = reply.reply_markup(keyboard);
+    }
+    let sent = reply.await?;
+
+    searches.insert(msg.chat.id, sent.id, SearchQuery(query.to_string()));
+
+    Ok(())
+}
+
+/// Replaces the results with the next ones.
+pub async fn search_callback(
+    bot: Bot,
+    q: CallbackQuery,
+    db: Arc<Database>,
+    notion_clients: Arc<NotionClients>,
+    searches: Arc<SearchQueries>,
+) -> HandlerResult {
+    let (Some(cursor), Some(message)) = (
+        q.data
+            .as_deref()
+            .and_then(|data| data.strip_prefix(SEARCH_CALLBACK_PREFIX)),
+        q.message,
+    ) else {
+        return Ok(());
+    };
+
+    let Some(SearchQuery(query)) = searches.get(message.chat.id, message.id) else {
+        bot.answer_callback_query(q.id)
+            .text("This search is too old, please search again")
+            .show_alert(true)
+            .await?;
+        return Ok(());
+    };
+
+    let (text, keyboard) =
+        match search(&db, &notion_clients, message.chat.id, &query, Some(cursor)).await {
+            Ok(results) => results,
+            Err(err) => {
+                log::error!("could not search for chat {}: {err}", message.chat.id);
+                bot.answer_callback_query(q.id)
+                    .text(err.user_message())
+                    .show_alert(true)
+                    .await?;
+                return Ok(());
+            }
+        };
+
+    bot.answer_callback_query(q.id).await?;
+    let mut edit = bot
+        .edit_message_text(message.chat.id, message.id, text)
+        .disable_web_page_preview(true);
+    if let Some(keyboard) = keyboard {
+        edit = edit.reply_markup(keyboard);
+    }
+    edit.await?;
+
+    Ok(())
+}
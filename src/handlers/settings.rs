@@ -0,0 +1,156 @@
+use std::sync::Arc;
+
+use teloxide::{
+    prelude::*,
+    types::{InlineKeyboardButton, InlineKeyboardMarkup},
+};
+
+use crate::db::{Database, UserSettings};
+use crate::storage::ImageHost;
+
+type HandlerResult = Result<(), Box<dyn std::error::Error + Send + Sync>>;
+
+pub const SETTINGS_CALLBACK_PREFIX: &str = "settings:";
+
+/// Text from images is only offered if OCR is set up, see `Ocr::from_env`.
+fn settings_keyboard(settings: &UserSettings, ocr_available: bool) -> InlineKeyboardMarkup {
+    let button = |label: String, key: &str| {
+        vec![InlineKeyboardButton::callback(
+            label,
+            format!("{SETTINGS_CALLBACK_PREFIX}{key}"),
+        )]
+    };
+    let toggle = |label: &str, key: &str, value: bool| {
+        button(
+            format!("{label}: {}", if value { "on" } else { "off" }),
+            key,
+        )
+    };
+
+    let mut rows = vec![
+        toggle("Bookmark block", "add_bookmark", settings.add_bookmark),
+        toggle(
+            "Include full text",
+            "include_full_text",
+            settings.include_full_text,
+        ),
+        toggle(
+            "Create unknown tags",
+            "create_unknown_tags",
+            settings.create_unknown_tags,
+        ),
+        toggle(
+            "@mentions as tags",
+            "mentions_as_tags",
+            settings.mentions_as_tags,
+        ),
+        toggle("Link preview", "link_preview", settings.link_preview),
+    ];
+    if ocr_available {
+        rows.push(toggle("Text from images", "ocr", settings.ocr));
+    }
+    rows.extend([
+        toggle(
+            "Article text",
+            "extract_articles",
+            settings.extract_articles,
+        ),
+        toggle("Archive pages", "archive_pages", settings.archive_pages),
+        button(
+            format!("Replies: {}", settings.reply_verbosity.as_str()),
+            "reply_verbosity",
+        ),
+        button(format!("Timezone: {}", settings.timezone), "timezone"),
+    ]);
+
+    InlineKeyboardMarkup::new(rows)
+}
+
+pub async fn send_settings(
+    bot: Bot,
+    msg: Message,
+    db: Arc<Database>,
+    image_host: Arc<ImageHost>,
+) -> HandlerResult {
+    let settings = db.get_settings(&msg.chat.id.to_string())?;
+
+    bot.send_message(msg.chat.id, "Tap a setting to change it")
+        .reply_markup(settings_keyboard(&settings, image_host.ocr.is_some()))
+        .await?;
+
+    Ok(())
+}
+
+pub async fn settings_callback(
+    bot: Bot,
+    q: CallbackQuery,
+    db: Arc<Database>,
+    image_host: Arc<ImageHost>,
+) -> HandlerResult {
+    let (Some(key), Some(message)) = (
+        q.data
+            .as_deref()
+            .and_then(|data| data.strip_prefix(SETTINGS_CALLBACK_PREFIX)),
+        q.message,
+    ) else {
+        return Ok(());
+    };
+
+    let user_id = message.chat.id.to_string();
+    let mut settings = db.get_settings(&user_id)?;
+
+    match key {
+        "add_bookmark" => settings.add_bookmark = !settings.add_bookmark,
+        "include_full_text" => settings.include_full_text = !settings.include_full_text,
+        "create_unknown_tags" => settings.create_unknown_tags = !settings.create_unknown_tags,
+        "mentions_as_tags" => settings.mentions_as_tags = !settings.mentions_as_tags,
+        "link_preview" => settings.link_preview = !settings.link_preview,
+        "ocr" if image_host.ocr.is_some() => settings.ocr = !settings.ocr,
+        "extract_articles" => settings.extract_articles = !settings.extract_articles,
+        "archive_pages" => settings.archive_pages = !settings.archive_pages,
+        "reply_verbosity" => settings.reply_verbosity = settings.reply_verbosity.next(),
+        "timezone" => {
+            bot.answer_callback_query(q.id)
+                .text("Change your timezone with /timezone, e.g. /timezone Europe/Berlin")
+                .show_alert(true)
+                .await?;
+            return Ok(());
+        }
+        _ => {
+            bot.answer_callback_query(q.id).await?;
+            return Ok(());
+        }
+    };
+
+    db.save_settings(&user_id, &settings)?;
+
+    bot.answer_callback_query(q.id).await?;
+    bot.edit_message_reply_markup(message.chat.id, message.id)
+        .reply_markup(settings_keyboard(&settings, image_host.ocr.is_some()))
+        .await?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn labels(keyboard: &InlineKeyboardMarkup) -> Vec<&str> {
+        keyboard
+            .inline_keyboard
+            .iter()
+            .map(|row| row[0].text.as_str())
+            .collect()
+    }
+
+    #[test]
+    fn text_from_images_is_only_offered_with_ocr() {
+        let settings = UserSettings::default();
+
+        assert!(labels(&settings_keyboard(&settings, true)).contains(&"Text from images: on"));
+        assert!(!labels(&settings_keyboard(&settings, false))
+            .iter()
+            .any(|label| label.starts_with("Text from images")));
+    }
+}
@@ -1,16 +1,24 @@
 use bot::run_bot;
 use db::Database;
 use dotenvy::dotenv;
-use img_push::ImgPush;
+use notion::NotionClients;
+use outbox::run_outbox;
 use std::env;
 use std::sync::Arc;
+use storage::ImageHost;
+use teloxide::Bot;
 
 mod bot;
 mod constants;
 mod db;
+mod error;
 mod handlers;
-mod img_push;
 mod notion;
+mod outbox;
+mod storage;
+mod tag_rules;
+mod text;
+mod web;
 
 #[tokio::main]
 async fn main() -> Result<(), String> {
@@ -18,18 +26,27 @@ async fn main() -> Result<(), String> {
 
     pretty_env_logger::init();
 
-    if env::var("IMG_PUSH_URL").is_err() || env::var("TELOXIDE_TOKEN").is_err() {
+    if env::var("TELOXIDE_TOKEN").is_err() {
         dotenv().expect(".env file not found");
     }
 
-    let img_push_url = env::var("IMG_PUSH_URL").expect("IMG_PUSH_URL not set");
     env::var("TELOXIDE_TOKEN").expect("TELOXIDE_TOKEN not set");
     let db_path = env::var("ADD_TO_NOTION_DB_PATH").unwrap_or("db/db.sqlite".to_string());
 
     let db = Arc::new(Database::new(&db_path).unwrap());
-    let img_push = Arc::new(ImgPush::new(img_push_url));
+    let image_host = Arc::new(ImageHost::from_env()?);
+    let notion_clients = Arc::new(NotionClients::default());
 
-    run_bot(db, img_push).await;
+    let bot = Bot::from_env();
+
+    tokio::spawn(run_outbox(
+        bot.clone(),
+        db.clone(),
+        image_host.clone(),
+        notion_clients.clone(),
+    ));
+
+    run_bot(bot, db, image_host, notion_clients).await;
 
     log::info!("Closing bot... Goodbye!");
 
@@ -0,0 +1,207 @@
+//! Blocks as json, for what the notion crate cannot express, e.g. toggles with children.
+
+use serde_json::{json, Value};
+
+use super::PageFile;
+
+// notion limits the content of a single text object
+pub(super) const MAX_TEXT_LENGTH: usize = 2000;
+/// notion rejects blocks with more text objects
+const MAX_TEXT_OBJECTS: usize = 100;
+
+/// Splits text into paragraphs that fit into a text object.
+pub(super) fn split_text(text: &str) -> Vec<String> {
+    text.split("\n\n")
+        .map(str::trim)
+        .filter(|paragraph| !paragraph.is_empty())
+        .flat_map(|paragraph| {
+            let chars: Vec<char> = paragraph.chars().collect();
+            chars
+                .chunks(MAX_TEXT_LENGTH)
+                .map(|chunk| chunk.iter().collect::<String>())
+                .collect::<Vec<String>>()
+        })
+        .collect()
+}
+
+/// Longer content is split into several text objects,
+/// what does not fit into a block is cut off, e.g. of a very long code block.
+fn rich_text(content: &str) -> Value {
+    let chars: Vec<char> = content.chars().collect();
+
+    chars
+        .chunks(MAX_TEXT_LENGTH)
+        .take(MAX_TEXT_OBJECTS)
+        .map(|chunk| {
+            let content: String = chunk.iter().collect();
+            json!({ "type": "text", "text": { "content": content } })
+        })
+        .collect()
+}
+
+pub(super) fn paragraph_block(content: &str) -> Value {
+    json!({ "type": "paragraph", "paragraph": { "rich_text": rich_text(content) } })
+}
+
+/// A toggle that is collapsed in notion, with the text as paragraphs inside.
+pub fn toggle_block(title: &str, text: &str) -> Value {
+    let children: Vec<Value> = split_text(text)
+        .iter()
+        .map(|paragraph| paragraph_block(paragraph))
+        .collect();
+
+    json!({
+        "type": "toggle",
+        "toggle": { "rich_text": rich_text(title), "children": children }
+    })
+}
+
+/// A heading that is collapsed in notion, the blocks inside are appended once it exists.
+pub(super) fn toggle_heading(title: &str) -> Value {
+    json!({
+        "type": "heading_3",
+        "heading_3": { "rich_text": rich_text(title), "is_toggleable": true }
+    })
+}
+
+/// Takes the children out of the block, so they can be appended once the block exists.
+pub(super) fn take_children(block: &mut Value) -> Vec<Value> {
+    let Some(kind) = block["type"].as_str().map(str::to_string) else {
+        return vec![];
+    };
+
+    match block[kind]
+        .as_object_mut()
+        .and_then(|content| content.remove("children"))
+    {
+        Some(Value::Array(children)) => children,
+        _ => vec![],
+    }
+}
+
+pub(super) fn bookmark_block(url: &str) -> Value {
+    json!({ "type": "bookmark", "bookmark": { "url": url } })
+}
+
+pub(super) fn image_block(image: &PageFile) -> Value {
+    json!({ "type": "image", "image": file_object(image) })
+}
+
+pub(super) fn file_block(file: &PageFile, name: &str) -> Value {
+    let mut file = file_object(file);
+    file["name"] = json!(name);
+
+    json!({ "type": "file", "file": file })
+}
+
+fn file_object(file: &PageFile) -> Value {
+    match file {
+        PageFile::External(url) => json!({ "type": "external", "external": { "url": url } }),
+        PageFile::FileUpload(id) => json!({ "type": "file_upload", "file_upload": { "id": id } }),
+    }
+}
+
+/// Content taken from elsewhere, e.g. the text of a web article.
+#[derive(Clone, Debug, PartialEq)]
+pub enum ContentBlock {
+    /// levels 1 to 3 like in notion
+    Heading {
+        level: u8,
+        text: String,
+    },
+    Paragraph(String),
+    BulletedListItem(String),
+    NumberedListItem(String),
+    Quote(String),
+    Code {
+        language: String,
+        text: String,
+    },
+    /// an external image or one uploaded to notion
+    Image(PageFile),
+    /// url of a video notion can embed, e.g. on youtube
+    Video(String),
+    ToDo {
+        text: String,
+        checked: bool,
+    },
+    Divider,
+}
+
+impl ContentBlock {
+    pub fn to_json(&self) -> Value {
+        let text_block = |kind: &str, text: &str| json!({ "type": kind, kind: { "rich_text": rich_text(text) } });
+
+        match self {
+            ContentBlock::Heading { level, text } => {
+                text_block(&format!("heading_{}", level.clamp(&1, &3)), text)
+            }
+            ContentBlock::Paragraph(text) => paragraph_block(text),
+            ContentBlock::BulletedListItem(text) => text_block("bulleted_list_item", text),
+            ContentBlock::NumberedListItem(text) => text_block("numbered_list_item", text),
+            ContentBlock::Quote(text) => text_block("quote", text),
+            ContentBlock::Code { language, text } => json!({
+                "type": "code",
+                "code": { "rich_text": rich_text(text), "language": language }
+            }),
+            ContentBlock::Image(image) => image_block(image),
+            ContentBlock::Video(url) => json!({
+                "type": "video",
+                "video": { "type": "external", "external": { "url": url } }
+            }),
+            ContentBlock::ToDo { text, checked } => json!({
+                "type": "to_do",
+                "to_do": { "rich_text": rich_text(text), "checked": checked }
+            }),
+            ContentBlock::Divider => json!({ "type": "divider", "divider": {} }),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn toggle_contains_paragraphs() {
+        let block = toggle_block("Text in image", "first\n\nsecond");
+
+        assert_eq!(
+            "Text in image",
+            block["toggle"]["rich_text"][0]["text"]["content"]
+        );
+        let children = block["toggle"]["children"].as_array().unwrap();
+        assert_eq!(2, children.len());
+        assert_eq!(
+            "second",
+            children[1]["paragraph"]["rich_text"][0]["text"]["content"]
+        );
+    }
+
+    #[test]
+    fn children_are_taken_out() {
+        let mut block = toggle_block("Text in image", "first\n\nsecond");
+
+        assert_eq!(2, take_children(&mut block).len());
+        assert!(block["toggle"].get("children").is_none());
+        assert!(take_children(&mut paragraph_block("text")).is_empty());
+    }
+
+    #[test]
+    fn long_text_is_split_into_text_objects() {
+        let block = ContentBlock::Code {
+            language: "rust".to_string(),
+            text: "a".repeat(MAX_TEXT_LENGTH + 1),
+        }
+        .to_json();
+
+        assert_eq!("rust", block["code"]["language"]);
+        assert_eq!(2, block["code"]["rich_text"].as_array().unwrap().len());
+
+        let block = paragraph_block(&"a".repeat(MAX_TEXT_LENGTH * (MAX_TEXT_OBJECTS + 1)));
+        assert_eq!(
+            MAX_TEXT_OBJECTS,
+            block["paragraph"]["rich_text"].as_array().unwrap().len()
+        );
+    }
+}
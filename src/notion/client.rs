@@ -1,18 +1,79 @@
-use super::NewPage;
-use anyhow::{anyhow, Result};
-use rusticnotion::{
-    models::{
-        block::{BookmarkFields, CreateBlock, ExternalFileObject},
-        properties::{PropertyConfiguration, PropertyValue},
-        search::NotionSearch,
-        Database, Page, PageCreateRequest, Properties,
-    },
-    NotionApi,
+use super::{
+    blocks::{bookmark_block, file_block, image_block, take_children, toggle_heading},
+    new_page::{empty_id, select_value, tags_property, ARCHIVE_PROPERTY},
+    query::QueryResponse,
+    JournalPages, NewEntry, NewPage, PageFile, QueryResults, RateLimiter, SchemaCache,
 };
-use std::collections::HashMap;
+use crate::error::{BotError, Result};
+use chrono::NaiveDate;
+use reqwest::{
+    header::{HeaderValue, RETRY_AFTER},
+    multipart, RequestBuilder,
+};
+use rusticnotion::models::{
+    properties::{PropertyConfiguration, PropertyValue, SelectOption},
+    Database, ListResponse, Object as NotionObject, Page, PageCreateRequest, Properties,
+};
+use serde::{
+    de::{DeserializeOwned, IgnoredAny},
+    Deserialize,
+};
+use serde_json::json;
+use std::{collections::HashMap, future::Future, sync::Arc, time::Duration};
+
+const MAX_RATE_LIMIT_RETRIES: u32 = 3;
+/// used when notion does not send a Retry-After header, doubled for each retry
+const RATE_LIMIT_BACKOFF: Duration = Duration::from_secs(1);
+
+// all requests are sent directly, so the Retry-After of rate limited ones is read
+const NOTION_API_URL: &str = "https://api.notion.com/v1";
+const NOTION_VERSION: &str = "2022-06-28";
+/// notion accepts at most 100 blocks per request
+const MAX_BLOCKS_PER_REQUEST: usize = 100;
+
+#[derive(Deserialize)]
+struct NotionErrorResponse {
+    message: String,
+}
+
+#[derive(Deserialize)]
+struct FileUpload {
+    id: String,
+}
+
+/// A block or page, of which only the id is needed.
+#[derive(Deserialize)]
+struct Object {
+    id: String,
+}
+
+#[derive(Deserialize)]
+struct ObjectList {
+    results: Vec<Object>,
+}
+
+/// A page created by `Notion::create_page`.
+pub struct CreatedPage {
+    pub page: Page,
+    /// what could not be added once the page existed, e.g. the blocks beyond the first 100
+    pub warnings: Vec<String>,
+}
+
+/// An entry appended by `Notion::append_entry`.
+pub struct AppendedEntry {
+    pub heading_id: String,
+    /// what could not be added under the heading
+    pub warnings: Vec<String>,
+}
 
 pub struct Notion {
-    pub api: NotionApi,
+    http: reqwest::Client,
+    /// `NOTION_API_URL`, a local server in tests
+    api_url: String,
+    token: String,
+    limiter: Arc<RateLimiter>,
+    schema_cache: Arc<SchemaCache>,
+    journal_pages: Arc<JournalPages>,
 }
 
 impl Notion {
@@ -28,15 +89,350 @@ impl Notion {
         db_has_expected_properties
     }
 
-    pub fn new(api_token: String) -> Self {
-        let api = NotionApi::new(api_token).unwrap();
+    pub fn new(
+        api_token: String,
+        limiter: Arc<RateLimiter>,
+        schema_cache: Arc<SchemaCache>,
+        journal_pages: Arc<JournalPages>,
+    ) -> Result<Self> {
+        // a token that is no valid header would fail every request
+        HeaderValue::from_str(&format!("Bearer {api_token}")).map_err(|_| BotError::NotionAuth)?;
+
+        Ok(Notion {
+            http: reqwest::Client::new(),
+            api_url: NOTION_API_URL.to_string(),
+            token: api_token,
+            limiter,
+            schema_cache,
+            journal_pages,
+        })
+    }
+
+    pub fn token(&self) -> &str {
+        &self.token
+    }
+
+    fn url(&self, path: &str) -> String {
+        format!("{}/{path}", self.api_url)
+    }
+
+    /// Runs a request once the rate limiter allows it, rate limited requests are retried.
+    async fn request<T, F, Fut>(&self, request: F) -> Result<T>
+    where
+        F: Fn() -> Fut,
+        Fut: Future<Output = Result<T>>,
+    {
+        let mut backoff = RATE_LIMIT_BACKOFF;
+        let mut retries = 0;
+
+        loop {
+            self.limiter.acquire(&self.token).await;
+
+            match request().await {
+                Err(BotError::NotionRateLimited { retry_after })
+                    if retries < MAX_RATE_LIMIT_RETRIES =>
+                {
+                    let wait = retry_after.unwrap_or(backoff);
+                    log::warn!("rate limited by notion, retrying in {wait:?}");
+
+                    self.limiter.pause(&self.token, wait);
+                    backoff *= 2;
+                    retries += 1;
+                }
+                result => return result,
+            }
+        }
+    }
+
+    /// Sends a request to the notion api and maps its errors.
+    async fn send<T: DeserializeOwned>(&self, request: RequestBuilder) -> Result<T> {
+        let response = request
+            .bearer_auth(&self.token)
+            .header("Notion-Version", NOTION_VERSION)
+            .send()
+            .await
+            .map_err(|err| BotError::NotionUnavailable(err.without_url().to_string()))?;
+
+        let status = response.status();
+        if status.is_success() {
+            return response
+                .json::<T>()
+                .await
+                .map_err(|err| BotError::Notion(err.without_url().to_string()));
+        }
+
+        let retry_after = response
+            .headers()
+            .get(RETRY_AFTER)
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| value.parse().ok())
+            .map(Duration::from_secs);
+        let message = match response.json::<NotionErrorResponse>().await {
+            Ok(error) => error.message,
+            Err(_) => status.to_string(),
+        };
+
+        match BotError::from_notion_status(status.as_u16(), message) {
+            BotError::NotionRateLimited { .. } => Err(BotError::NotionRateLimited { retry_after }),
+            err => Err(err),
+        }
+    }
+
+    /// Uploads a file to notion and returns the id of the upload,
+    /// which has to be attached to a page or block within an hour.
+    pub async fn upload_file(
+        &self,
+        filename: &str,
+        content_type: &str,
+        content: Vec<u8>,
+    ) -> Result<String> {
+        let upload: FileUpload = self
+            .request(|| {
+                self.send(self.http.post(self.url("file_uploads")).json(&json!({
+                    "mode": "single_part",
+                    "filename": filename,
+                    "content_type": content_type,
+                })))
+            })
+            .await?;
+
+        self.request(|| async {
+            let file = multipart::Part::bytes(content.clone())
+                .file_name(filename.to_string())
+                .mime_str(content_type)
+                .map_err(|err| BotError::Notion(err.to_string()))?;
+
+            self.send::<FileUpload>(
+                self.http
+                    .post(self.url(&format!("file_uploads/{}/send", upload.id)))
+                    .multipart(multipart::Form::new().part("file", file)),
+            )
+            .await
+        })
+        .await?;
+
+        Ok(upload.id)
+    }
+
+    /// Appends blocks to a page or block, in chunks notion accepts.
+    /// The children of the blocks are appended once their block exists,
+    /// notion only takes two levels of nesting at once.
+    pub async fn append_blocks(
+        &self,
+        block_id: &str,
+        mut blocks: Vec<serde_json::Value>,
+    ) -> Result<()> {
+        let nested: Vec<Vec<serde_json::Value>> = blocks.iter_mut().map(take_children).collect();
+
+        for (chunk, nested) in blocks
+            .chunks(MAX_BLOCKS_PER_REQUEST)
+            .zip(nested.chunks(MAX_BLOCKS_PER_REQUEST))
+        {
+            let created: ObjectList = self
+                .request(|| {
+                    self.send(
+                        self.http
+                            .patch(self.url(&format!("blocks/{block_id}/children")))
+                            .json(&json!({ "children": chunk })),
+                    )
+                })
+                .await?;
+
+            for (block, children) in created.results.iter().zip(nested) {
+                if !children.is_empty() {
+                    Box::pin(self.append_blocks(&block.id, children.clone())).await?;
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Appends the entry to the page as toggle heading.
+    pub async fn append_entry(&self, page_id: &str, entry: NewEntry) -> Result<AppendedEntry> {
+        let heading = entry.heading();
+        let children = entry.into_blocks();
+        let body = json!({ "children": [toggle_heading(&heading)] });
 
-        Notion { api }
+        let created: ObjectList = self
+            .request(|| {
+                self.send(
+                    self.http
+                        .patch(self.url(&format!("blocks/{page_id}/children")))
+                        .json(&body),
+                )
+            })
+            .await?;
+        let heading_id = created
+            .results
+            .first()
+            .map(|block| block.id.clone())
+            .ok_or(BotError::Notion("no block was created".to_string()))?;
+
+        // the heading exists already, so failing here would add it twice when retried
+        let mut warnings = vec![];
+        if let Err(err) = self.append_blocks(&heading_id, children).await {
+            log::error!("could not append blocks to heading {heading_id}: {err}");
+            warnings.push(format!("Some content is missing under the entry: {err}"));
+        }
+
+        Ok(AppendedEntry {
+            heading_id,
+            warnings,
+        })
+    }
+
+    /// Runs a query of the database endpoint, e.g. from `search_query`.
+    pub async fn query_database(
+        &self,
+        database_id: &str,
+        mut query: serde_json::Value,
+        page_size: u32,
+        cursor: Option<&str>,
+    ) -> Result<QueryResults> {
+        query["page_size"] = json!(page_size);
+        if let Some(cursor) = cursor {
+            query["start_cursor"] = json!(cursor);
+        }
+
+        let response: QueryResponse = self
+            .request(|| {
+                self.send(
+                    self.http
+                        .post(self.url(&format!("databases/{database_id}/query")))
+                        .json(&query),
+                )
+            })
+            .await?;
+
+        Ok(response.into())
+    }
+
+    /// Moves the page to the trash of notion.
+    pub async fn archive_page(&self, page_id: &str) -> Result<()> {
+        self.request(|| {
+            self.send::<IgnoredAny>(
+                self.http
+                    .patch(self.url(&format!("pages/{page_id}")))
+                    .json(&json!({ "archived": true })),
+            )
+        })
+        .await?;
+
+        Ok(())
+    }
+
+    /// Replaces the tags of a page, no tags remove all of them.
+    /// Returns the tags that were left out, as they are no options of the database
+    /// and `create_unknown_tags` is off. If all of them are left out, the page keeps its tags.
+    pub async fn set_tags(
+        &self,
+        page_id: &str,
+        database: &Database,
+        tags: &[String],
+        create_unknown_tags: bool,
+    ) -> Result<Vec<String>> {
+        let existing_tags = existing_tags(database);
+        let unknown_tags: Vec<String> = tags
+            .iter()
+            .filter(|tag| !create_unknown_tags && select_value(&existing_tags, tag).id.is_none())
+            .cloned()
+            .collect();
+
+        let tags = match tags_property(&existing_tags, tags, create_unknown_tags) {
+            Some(tags) => tags,
+            None if tags.is_empty() => PropertyValue::MultiSelect {
+                id: empty_id(),
+                multi_select: Some(vec![]),
+            },
+            None => return Ok(unknown_tags),
+        };
+        let body = json!({ "properties": { "Tags": tags } });
+
+        self.request(|| {
+            self.send::<IgnoredAny>(
+                self.http
+                    .patch(self.url(&format!("pages/{page_id}")))
+                    .json(&body),
+            )
+        })
+        .await?;
+
+        Ok(unknown_tags)
+    }
+
+    /// The page of the day in a journal database, which is created with the first capture of the day.
+    pub async fn journal_page(&self, database_id: &str, date: NaiveDate) -> Result<String> {
+        let database = self
+            .get_cached_database_by_id(database_id.to_string())
+            .await?;
+
+        let date_property =
+            journal_date_property(&database).ok_or_else(|| BotError::MissingProperties {
+                found: database.properties.keys().cloned().collect(),
+            })?;
+        let day = date.format("%Y-%m-%d").to_string();
+
+        // held until the page exists, so messages sent at the same time find it
+        let journal_page = self.journal_pages.day(&self.token, database_id, date);
+        let mut journal_page = journal_page.lock().await;
+        if let Some(page_id) = journal_page.as_ref() {
+            return Ok(page_id.clone());
+        }
+
+        let pages = self
+            .query_database(
+                database_id,
+                json!({ "filter": { "property": date_property, "date": { "equals": day } } }),
+                1,
+                None,
+            )
+            .await?;
+        if let Some(page) = pages.pages.first() {
+            *journal_page = Some(page.id.clone());
+            return Ok(page.id.clone());
+        }
+
+        let new_page = NewPage {
+            name: Some(day),
+            tags: None,
+            url: None,
+            image: None,
+            archive: None,
+            properties: HashMap::new(),
+            date: Some(date),
+            date_property: Some(date_property),
+            full_text: None,
+            add_bookmark: false,
+            create_unknown_tags: false,
+            extra_blocks: vec![],
+            default_properties: HashMap::new(),
+            template_blocks: vec![],
+            database,
+        };
+        // the entries are appended to the page, it has no content of its own
+        let page = self.create_page(new_page).await?.page;
+        *journal_page = Some(page.id.to_string());
+
+        Ok(page.id.to_string())
+    }
+
+    /// Forgets the cached journal page of the day, e.g. when it was deleted in notion.
+    pub fn forget_journal_page(&self, database_id: &str, date: NaiveDate) {
+        self.journal_pages.forget(&self.token, database_id, date);
     }
 
     pub async fn get_database_by_id(&self, database_id: String) -> Result<Database> {
-        let search = NotionSearch::filter_by_databases();
-        let response = self.api.search(search).await?.only_databases();
+        let response = self
+            .request(|| {
+                self.send::<ListResponse<NotionObject>>(
+                    self.http
+                        .post(self.url("search"))
+                        .json(&json!({ "filter": { "property": "object", "value": "database" } })),
+                )
+            })
+            .await?
+            .only_databases();
 
         if let Some(database) = response
             .results
@@ -45,20 +441,33 @@ impl Notion {
         {
             Ok(database.to_owned())
         } else {
-            Err(anyhow!("database does not exist"))
+            // the search only finds databases shared with the integration
+            Err(BotError::NotionNotShared)
         }
     }
 
-    pub async fn create_page(&self, new_page: NewPage) -> Result<Page, String> {
-        let existing_tags = match new_page.database.properties.get("Tags").unwrap() {
-            PropertyConfiguration::MultiSelect {
-                id: _,
-                multi_select,
-            } => multi_select.options.clone(),
-            _ => vec![],
-        };
+    /// Like `get_database_by_id`, but uses the cached schema if it is recent enough.
+    pub async fn get_cached_database_by_id(&self, database_id: String) -> Result<Database> {
+        if let Some(database) = self.schema_cache.get(&self.token, &database_id) {
+            return Ok(database);
+        }
 
-        let properties: Properties = Properties {
+        let database = self.get_database_by_id(database_id.clone()).await?;
+        self.schema_cache
+            .insert(&self.token, &database_id, database.clone());
+
+        Ok(database)
+    }
+
+    /// Drops the cached schema, e.g. after the database was changed in notion.
+    pub fn invalidate_schema(&self, database_id: &str) {
+        self.schema_cache.invalidate(&self.token, database_id);
+    }
+
+    pub async fn create_page(&self, new_page: NewPage) -> Result<CreatedPage> {
+        let existing_tags = existing_tags(&new_page.database);
+
+        let mut properties: Properties = Properties {
             properties: [
                 ("Name".to_string(), new_page.get_name_property()),
                 ("URL".to_string(), new_page.get_url_property()),
@@ -72,51 +481,501 @@ impl Notion {
             .filter_map(|(key, property)| property.as_ref().map(|v| (key.clone(), v.clone())))
             .collect::<HashMap<String, PropertyValue>>(),
         };
+        let text_blocks = new_page.get_text_blocks();
+        let archive_upload = new_page.get_archive_upload().map(str::to_string);
+        let links_archive_block = new_page.links_archive_block();
 
-        let image_block: Option<CreateBlock> = if let Some(image_url) = new_page.image_url {
-            Some(CreateBlock::Image {
-                image: rusticnotion::models::block::FileObject::External {
-                    external: ExternalFileObject { url: image_url },
-                },
-            })
-        } else {
-            None
+        if let Some((name, date)) = new_page.get_date_property() {
+            properties.properties.insert(name, date);
+        }
+        if let Some((name, archive)) = new_page.get_archive_property() {
+            properties.properties.insert(name, archive);
+        }
+        properties.properties.extend(new_page.properties);
+        for (name, value) in new_page.default_properties {
+            properties.properties.entry(name).or_insert(value);
+        }
+
+        let text_blocks = text_blocks
+            .iter()
+            .map(serde_json::to_value)
+            .collect::<serde_json::Result<Vec<_>>>()
+            .map_err(|err| BotError::Notion(err.to_string()))?;
+        let mut children: Vec<serde_json::Value> = new_page
+            .image
+            .iter()
+            .map(image_block)
+            .chain(
+                new_page
+                    .url
+                    .iter()
+                    .filter(|_| new_page.add_bookmark)
+                    .map(|url| bookmark_block(url)),
+            )
+            .chain(text_blocks)
+            .chain(new_page.template_blocks)
+            .collect();
+        // a page is created with at most 100 blocks, the rest is appended once it exists
+        let mut later_blocks = children.split_off(children.len().min(MAX_BLOCKS_PER_REQUEST));
+
+        let parent = rusticnotion::models::Parent::Database {
+            database_id: new_page.database.id,
         };
 
-        let bookmark: Option<CreateBlock> = if let Some(url) = new_page.url {
-            Some(CreateBlock::Bookmark {
-                bookmark: BookmarkFields {
-                    url,
-                    caption: vec![],
-                },
+        let resp: Page = self
+            .request(|| async {
+                let page = PageCreateRequest {
+                    parent: parent.clone(),
+                    properties: properties.clone(),
+                    children: None,
+                };
+
+                let upload_id = match &new_page.image {
+                    Some(PageFile::FileUpload(upload_id)) => Some(upload_id.as_str()),
+                    _ => None,
+                };
+
+                self.send(self.http.post(self.url("pages")).json(&page_request_json(
+                    page,
+                    upload_id,
+                    archive_upload.as_deref(),
+                    &children,
+                )?))
+                .await
             })
-        } else {
-            None
+            .await?;
+
+        later_blocks.extend(new_page.extra_blocks);
+        match &new_page.archive {
+            Some(archive) if !links_archive_block => {
+                later_blocks.push(file_block(archive, ARCHIVE_PROPERTY))
+            }
+            _ => {}
+        }
+
+        // the page exists already, so failing here would create it twice when retried
+        let mut warnings = vec![];
+        if !later_blocks.is_empty() {
+            if let Err(err) = self.append_blocks(&resp.id.to_string(), later_blocks).await {
+                log::error!("could not append blocks to page {}: {err}", resp.id);
+                warnings.push(format!("Some content is missing on the page: {err}"));
+            }
+        }
+        if let (Some(archive), true) = (&new_page.archive, links_archive_block) {
+            if let Err(err) = self.link_archive_block(&resp.id.to_string(), archive).await {
+                log::error!("could not link the archive of page {}: {err}", resp.id);
+                warnings.push(format!("The archive is missing on the page: {err}"));
+            }
+        }
+
+        Ok(CreatedPage {
+            page: resp,
+            warnings,
+        })
+    }
+
+    /// Appends the archive to the page and links its block in the `Archive` property.
+    async fn link_archive_block(&self, page_id: &str, archive: &PageFile) -> Result<()> {
+        let created: ObjectList = self
+            .request(|| {
+                self.send(
+                    self.http
+                        .patch(self.url(&format!("blocks/{page_id}/children")))
+                        .json(&json!({ "children": [file_block(archive, ARCHIVE_PROPERTY)] })),
+                )
+            })
+            .await?;
+        let block_id = created
+            .results
+            .first()
+            .map(|block| block.id.replace('-', ""))
+            .ok_or(BotError::Notion("no block was created".to_string()))?;
+
+        let link = format!(
+            "https://www.notion.so/{}#{block_id}",
+            page_id.replace('-', "")
+        );
+        self.request(|| {
+            self.send::<IgnoredAny>(
+                self.http
+                    .patch(self.url(&format!("pages/{page_id}")))
+                    .json(&json!({ "properties": { ARCHIVE_PROPERTY: { "url": link } } })),
+            )
+        })
+        .await?;
+
+        Ok(())
+    }
+}
+
+/// The options of the Tags property, journal databases have none.
+fn existing_tags(database: &Database) -> Vec<SelectOption> {
+    match database.properties.get("Tags") {
+        Some(PropertyConfiguration::MultiSelect {
+            id: _,
+            multi_select,
+        }) => multi_select.options.clone(),
+        _ => vec![],
+    }
+}
+
+/// The Date property of a journal database, or the first property holding a date.
+/// Journal pages need a title as well.
+fn journal_date_property(database: &Database) -> Option<String> {
+    let is_date = |name: &str| {
+        matches!(
+            database.properties.get(name),
+            Some(PropertyConfiguration::Date { .. })
+        )
+    };
+
+    if !database.properties.contains_key("Name") {
+        return None;
+    }
+    if is_date("Date") {
+        return Some("Date".to_string());
+    }
+
+    let mut date_properties: Vec<&String> = database
+        .properties
+        .keys()
+        .filter(|name| is_date(name))
+        .collect();
+    // the properties are a map, so pick the same one every time
+    date_properties.sort();
+    date_properties.first().map(|name| name.to_string())
+}
+
+/// The request as json, with what the notion crate cannot express:
+/// an uploaded image as Image property, an uploaded archive as Archive property,
+/// and the blocks as json, see `blocks`.
+fn page_request_json(
+    page: PageCreateRequest,
+    upload_id: Option<&str>,
+    archive_upload: Option<&str>,
+    children: &[serde_json::Value],
+) -> Result<serde_json::Value> {
+    let mut page = serde_json::to_value(page).map_err(|err| BotError::Notion(err.to_string()))?;
+
+    if let Some(upload_id) = upload_id {
+        page["properties"]["Image"] = json!({
+            "files": [{ "name": "Image", "type": "file_upload", "file_upload": { "id": upload_id } }]
+        });
+    }
+    if let Some(archive_upload) = archive_upload {
+        page["properties"][ARCHIVE_PROPERTY] = json!({
+            "files": [{ "name": ARCHIVE_PROPERTY, "type": "file_upload", "file_upload": { "id": archive_upload } }]
+        });
+    }
+    page["children"] = json!(children);
+
+    Ok(page)
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::Value;
+
+    use super::*;
+    use crate::web::test_server::{serve_paths, serve_recorded};
+
+    const DATABASE_ID: &str = "0123456789abcdef0123456789abcdef";
+    /// the response of notion when a page is created
+    const PAGE: &str = r#"{"object":"page","id":"fedcba98-7654-3210-fedc-ba9876543210","created_time":"2024-06-03T08:00:00.000Z","last_edited_time":"2024-06-03T08:00:00.000Z","archived":false,"properties":{},"parent":{"type":"database_id","database_id":"01234567-89ab-cdef-0123-456789abcdef"},"url":"https://www.notion.so/fedcba9876543210fedcba9876543210"}"#;
+
+    fn database(properties: Value) -> Database {
+        serde_json::from_value(json!({
+            "object": "database",
+            "id": DATABASE_ID,
+            "created_time": "2024-06-01T00:00:00.000Z",
+            "last_edited_time": "2024-06-01T00:00:00.000Z",
+            "title": [],
+            "properties": properties
+        }))
+        .unwrap()
+    }
+
+    fn title() -> Value {
+        json!({ "id": "title", "name": "Name", "type": "title", "title": {} })
+    }
+
+    fn date(name: &str) -> Value {
+        json!({ "id": name, "name": name, "type": "date", "date": {} })
+    }
+
+    /// A client sending its requests to the local server, with the journal database cached.
+    fn notion(api_url: String) -> Notion {
+        let notion = Notion {
+            api_url,
+            ..Notion::new(
+                "token".to_string(),
+                Arc::new(RateLimiter::default()),
+                Arc::new(SchemaCache::default()),
+                Arc::new(JournalPages::default()),
+            )
+            .unwrap()
         };
+        notion.schema_cache.insert(
+            "token",
+            DATABASE_ID,
+            database(json!({ "Name": title(), "Date": date("Date") })),
+        );
+
+        notion
+    }
+
+    fn count(requests: &std::sync::Mutex<Vec<String>>, prefix: &str) -> usize {
+        requests
+            .lock()
+            .unwrap()
+            .iter()
+            .filter(|request| request.starts_with(prefix))
+            .count()
+    }
 
-        let blocks: Option<Vec<Option<CreateBlock>>> = Some([image_block, bookmark].to_vec());
+    #[test]
+    fn finds_journal_date_property() {
+        assert_eq!(
+            Some("Date".to_string()),
+            journal_date_property(&database(
+                json!({ "Name": title(), "Created": date("Created"), "Date": date("Date") })
+            ))
+        );
+        assert_eq!(
+            Some("Created".to_string()),
+            journal_date_property(&database(
+                json!({ "Name": title(), "Day": date("Day"), "Created": date("Created") })
+            ))
+        );
+        assert_eq!(
+            None,
+            journal_date_property(&database(json!({ "Date": date("Date") })))
+        );
+        assert_eq!(
+            None,
+            journal_date_property(&database(json!({ "Name": title() })))
+        );
+    }
 
-        let children = if let Some(blocks) = blocks {
-            let children = blocks
-                .iter()
-                .filter(|block| block.is_some())
-                .map(|block| block.to_owned().unwrap())
-                .collect::<Vec<CreateBlock>>();
-            Some(children)
-        } else {
-            None
+    fn bodies(requests: &std::sync::Mutex<Vec<String>>, prefix: &str) -> Vec<Value> {
+        requests
+            .lock()
+            .unwrap()
+            .iter()
+            .filter(|request| request.starts_with(prefix))
+            .map(|request| serde_json::from_str(request.split_once('\n').unwrap().1).unwrap())
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn long_pages_are_created_in_parts() {
+        let (api_url, requests) = serve_recorded(vec![
+            ("/pages", PAGE),
+            ("/blocks/", r#"{"results":[{"id":"block"}]}"#),
+        ])
+        .await;
+        let notion = notion(api_url);
+        let new_page = NewPage {
+            database: database(json!({ "Name": title(), "Image": { "id": "image", "name": "Image", "type": "files", "files": {} } })),
+            name: Some("Long".to_string()),
+            url: Some("https://example.com".to_string()),
+            image: Some(PageFile::FileUpload("upload".to_string())),
+            archive: None,
+            tags: None,
+            properties: HashMap::new(),
+            date: None,
+            date_property: None,
+            full_text: None,
+            add_bookmark: true,
+            create_unknown_tags: false,
+            extra_blocks: vec![json!({ "type": "divider", "divider": {} })],
+            default_properties: HashMap::new(),
+            template_blocks: (0..150)
+                .map(|i| json!({ "type": "paragraph", "paragraph": { "rich_text": [{ "type": "text", "text": { "content": i.to_string() } }] } }))
+                .collect(),
         };
 
-        let page = PageCreateRequest {
-            parent: rusticnotion::models::Parent::Database {
-                database_id: new_page.database.id,
-            },
-            properties,
-            children,
+        assert!(notion
+            .create_page(new_page)
+            .await
+            .unwrap()
+            .warnings
+            .is_empty());
+
+        let page = &bodies(&requests, "POST /pages")[0];
+        let children = page["children"].as_array().unwrap();
+        assert_eq!(MAX_BLOCKS_PER_REQUEST, children.len());
+        assert_eq!("upload", children[0]["image"]["file_upload"]["id"]);
+        assert_eq!("bookmark", children[1]["type"]);
+        assert_eq!(
+            "upload",
+            page["properties"]["Image"]["files"][0]["file_upload"]["id"]
+        );
+
+        // the rest of the template, then the extra blocks
+        let appended = &bodies(&requests, "PATCH /blocks/")[0]["children"];
+        assert_eq!(53, appended.as_array().unwrap().len());
+        assert_eq!(
+            "98",
+            appended[0]["paragraph"]["rich_text"][0]["text"]["content"]
+        );
+        assert_eq!("divider", appended[52]["type"]);
+    }
+
+    #[tokio::test]
+    async fn missing_blocks_are_reported() {
+        // appending to the page fails
+        let api_url = serve_paths(vec![("/pages", PAGE)]).await;
+        let notion = notion(api_url);
+        let new_page = NewPage {
+            database: database(json!({ "Name": title() })),
+            name: Some("Article".to_string()),
+            url: None,
+            image: None,
+            archive: None,
+            tags: None,
+            properties: HashMap::new(),
+            date: None,
+            date_property: None,
+            full_text: None,
+            add_bookmark: false,
+            create_unknown_tags: false,
+            extra_blocks: vec![json!({ "type": "divider", "divider": {} })],
+            default_properties: HashMap::new(),
+            template_blocks: vec![],
         };
 
-        let resp = self.api.create_page(page).await.unwrap();
+        let created = notion.create_page(new_page).await.unwrap();
+
+        assert_eq!(
+            "fedcba98-7654-3210-fedc-ba9876543210",
+            created.page.id.to_string()
+        );
+        assert_eq!(1, created.warnings.len());
+    }
+
+    #[tokio::test]
+    async fn missing_entry_blocks_are_reported() {
+        // the heading is created, appending under it fails
+        let api_url = serve_paths(vec![(
+            "/blocks/journal-page/",
+            r#"{"results":[{"id":"heading"}]}"#,
+        )])
+        .await;
+        let notion = notion(api_url);
+        let entry = NewEntry {
+            timestamp: "14:05".to_string(),
+            title: Some("Note".to_string()),
+            url: None,
+            image: None,
+            archive: None,
+            tags: None,
+            text: "Note".to_string(),
+            add_bookmark: false,
+            extra_blocks: vec![],
+        };
+
+        let appended = notion.append_entry("journal-page", entry).await.unwrap();
+
+        assert_eq!("heading", appended.heading_id);
+        assert_eq!(1, appended.warnings.len());
+    }
+
+    #[tokio::test]
+    async fn unknown_tags_are_reported_and_keep_the_tags() {
+        let (api_url, requests) = serve_recorded(vec![("/pages/", PAGE)]).await;
+        let notion = notion(api_url);
+        let database = database(json!({
+            "Name": title(),
+            "Tags": {
+                "id": "tags",
+                "name": "Tags",
+                "type": "multi_select",
+                "multi_select": { "options": [{ "id": "1", "name": "read", "color": "blue" }] }
+            }
+        }));
+        let tags = |tags: &[&str]| tags.iter().map(|tag| tag.to_string()).collect::<Vec<_>>();
+
+        let unknown = notion
+            .set_tags("page", &database, &tags(&["later"]), false)
+            .await
+            .unwrap();
+        assert_eq!(tags(&["later"]), unknown);
+        assert_eq!(0, count(&requests, "PATCH /pages/"));
+
+        let unknown = notion
+            .set_tags("page", &database, &tags(&["Read", "later"]), false)
+            .await
+            .unwrap();
+        assert_eq!(tags(&["later"]), unknown);
+        let body = &bodies(&requests, "PATCH /pages/")[0];
+        assert_eq!(
+            1,
+            body["properties"]["Tags"]["multi_select"]
+                .as_array()
+                .unwrap()
+                .len()
+        );
+
+        // `-` removes all tags
+        assert!(notion
+            .set_tags("page", &database, &[], false)
+            .await
+            .unwrap()
+            .is_empty());
+        let body = &bodies(&requests, "PATCH /pages/")[1];
+        assert!(body["properties"]["Tags"]["multi_select"]
+            .as_array()
+            .unwrap()
+            .is_empty());
+    }
+
+    #[tokio::test]
+    async fn finds_the_page_of_the_day() {
+        let (api_url, requests) = serve_recorded(vec![(
+            "/databases/",
+            r#"{"results":[{"id":"existing","url":"https://www.notion.so/existing","properties":{}}],"next_cursor":null}"#,
+        )])
+        .await;
+        let notion = notion(api_url);
+        let day = NaiveDate::from_ymd_opt(2024, 6, 3).unwrap();
+
+        assert_eq!(
+            "existing",
+            notion.journal_page(DATABASE_ID, day).await.unwrap()
+        );
+        assert_eq!(
+            "existing",
+            notion.journal_page(DATABASE_ID, day).await.unwrap()
+        );
+
+        // the second time the page is known already
+        assert_eq!(1, count(&requests, "POST /databases/"));
+        assert_eq!(0, count(&requests, "POST /pages"));
+    }
+
+    #[tokio::test]
+    async fn creates_the_page_of_the_day_once() {
+        let (api_url, requests) = serve_recorded(vec![
+            ("/databases/", r#"{"results":[],"next_cursor":null}"#),
+            ("/pages", PAGE),
+        ])
+        .await;
+        let notion = notion(api_url);
+        let day = NaiveDate::from_ymd_opt(2024, 6, 3).unwrap();
+
+        let (first, second) = tokio::join!(
+            notion.journal_page(DATABASE_ID, day),
+            notion.journal_page(DATABASE_ID, day)
+        );
+
+        assert_eq!(first.unwrap(), second.unwrap());
+        assert_eq!(1, count(&requests, "POST /databases/"));
+        assert_eq!(1, count(&requests, "POST /pages"));
 
-        Ok(resp)
+        // a deleted page is looked up again
+        notion.forget_journal_page(DATABASE_ID, day);
+        notion.journal_page(DATABASE_ID, day).await.unwrap();
+        assert_eq!(2, count(&requests, "POST /pages"));
     }
 }
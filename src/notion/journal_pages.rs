@@ -0,0 +1,69 @@
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+};
+
+use chrono::NaiveDate;
+
+/// the dates of users in other timezones are up to two days apart
+const KEPT_DAYS: i64 = 2;
+
+/// holds the id of the day's page once it is known
+type DayPage = Arc<tokio::sync::Mutex<Option<String>>>;
+
+/// The page of a day in a journal database, keyed by integration token, database id and day.
+/// Messages sent at the same time wait for each other, so the day's page is only created once.
+#[derive(Default)]
+pub struct JournalPages {
+    days: Mutex<HashMap<(String, String, NaiveDate), DayPage>>,
+}
+
+impl JournalPages {
+    /// The lock of the day's page.
+    pub fn day(&self, token: &str, database_id: &str, date: NaiveDate) -> DayPage {
+        let mut days = self.days.lock().unwrap();
+
+        // past days are not written to anymore
+        days.retain(|(_, _, day), _| (date - *day).num_days().abs() <= KEPT_DAYS);
+
+        days.entry((token.to_string(), database_id.to_string(), date))
+            .or_default()
+            .clone()
+    }
+
+    /// Forgets the page of the day, e.g. after it was deleted in notion.
+    pub fn forget(&self, token: &str, database_id: &str, date: NaiveDate) {
+        self.days
+            .lock()
+            .unwrap()
+            .remove(&(token.to_string(), database_id.to_string(), date));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn days_are_kept_apart_and_old_days_dropped() {
+        let pages = JournalPages::default();
+        let monday = NaiveDate::from_ymd_opt(2024, 6, 3).unwrap();
+
+        *pages.day("token", "1", monday).try_lock().unwrap() = Some("page".to_string());
+        assert_eq!(
+            Some("page".to_string()),
+            *pages.day("token", "1", monday).try_lock().unwrap()
+        );
+        assert_eq!(
+            None,
+            *pages.day("other token", "1", monday).try_lock().unwrap()
+        );
+
+        let friday = NaiveDate::from_ymd_opt(2024, 6, 7).unwrap();
+        pages.day("token", "1", friday);
+        assert_eq!(None, *pages.day("token", "1", monday).try_lock().unwrap());
+
+        pages.forget("token", "1", monday);
+        assert_eq!(None, *pages.day("token", "1", monday).try_lock().unwrap());
+    }
+}
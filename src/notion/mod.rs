@@ -1,5 +1,23 @@
+mod blocks;
 mod client;
+mod journal_pages;
+mod new_entry;
 mod new_page;
+mod properties;
+mod query;
+mod rate_limit;
+mod registry;
+mod schema_cache;
+mod template;
 
+pub use blocks::{toggle_block, ContentBlock};
 pub use client::Notion;
-pub use new_page::NewPage;
+pub use journal_pages::JournalPages;
+pub use new_entry::NewEntry;
+pub use new_page::{NewPage, PageFile};
+pub use properties::{find_property, parse_property_values};
+pub use query::{recent_query, search_query, FoundPage, QueryResults};
+pub use rate_limit::RateLimiter;
+pub use registry::NotionClients;
+pub use schema_cache::SchemaCache;
+pub use template::{PageTemplate, Placeholders};
@@ -0,0 +1,128 @@
+use serde_json::Value;
+
+use super::{
+    blocks::{bookmark_block, file_block, image_block, paragraph_block, split_text},
+    PageFile,
+};
+
+/// A message appended to a page instead of creating a page in a database,
+/// see `Notion::append_entry`.
+pub struct NewEntry {
+    /// when the message was sent, e.g. `2024-06-01 14:05`
+    pub timestamp: String,
+    pub title: Option<String>,
+    pub url: Option<String>,
+    pub image: Option<PageFile>,
+    /// snapshot of the linked web page
+    pub archive: Option<PageFile>,
+    pub tags: Option<Vec<String>>,
+    /// the whole message
+    pub text: String,
+    pub add_bookmark: bool,
+    /// e.g. the text recognized in images
+    pub extra_blocks: Vec<Value>,
+}
+
+impl NewEntry {
+    pub(super) fn heading(&self) -> String {
+        match self.title.as_deref().map(str::trim) {
+            Some(title) if !title.is_empty() => format!("{} · {title}", self.timestamp),
+            _ => self.timestamp.clone(),
+        }
+    }
+
+    /// The content of the message, placed inside the heading.
+    pub(super) fn into_blocks(self) -> Vec<Value> {
+        let mut blocks: Vec<Value> = self.image.iter().map(image_block).collect();
+
+        blocks.extend(
+            split_text(&self.text)
+                .iter()
+                .map(|text| paragraph_block(text)),
+        );
+        if let Some(tags) = self.tags.filter(|tags| !tags.is_empty()) {
+            blocks.push(paragraph_block(&format!("Tags: {}", tags.join(", "))));
+        }
+        if let (Some(url), true) = (&self.url, self.add_bookmark) {
+            blocks.push(bookmark_block(url));
+        }
+        blocks.extend(self.extra_blocks);
+        if let Some(archive) = &self.archive {
+            blocks.push(file_block(archive, "Archive"));
+        }
+
+        blocks
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::notion::toggle_block;
+
+    fn entry() -> NewEntry {
+        NewEntry {
+            timestamp: "2024-06-01 14:05".to_string(),
+            title: Some("Interesting article".to_string()),
+            url: Some("https://example.com/article".to_string()),
+            image: Some(PageFile::External("https://img.example/a.jpg".to_string())),
+            archive: Some(PageFile::FileUpload("upload".to_string())),
+            tags: Some(vec!["read".to_string(), "web".to_string()]),
+            text: "Interesting article\n\nhttps://example.com/article".to_string(),
+            add_bookmark: true,
+            extra_blocks: vec![toggle_block("Text in image", "recognized")],
+        }
+    }
+
+    #[test]
+    fn heading_has_timestamp_and_title() {
+        assert_eq!("2024-06-01 14:05 · Interesting article", entry().heading());
+
+        let untitled = NewEntry {
+            title: Some("  ".to_string()),
+            ..entry()
+        };
+        assert_eq!("2024-06-01 14:05", untitled.heading());
+    }
+
+    #[test]
+    fn blocks_are_in_order() {
+        let blocks = entry().into_blocks();
+        let kinds: Vec<&str> = blocks
+            .iter()
+            .map(|block| block["type"].as_str().unwrap())
+            .collect();
+
+        assert_eq!(
+            vec![
+                "image",
+                "paragraph",
+                "paragraph",
+                "paragraph",
+                "bookmark",
+                "toggle",
+                "file"
+            ],
+            kinds
+        );
+        assert_eq!(
+            "Tags: read, web",
+            blocks[3]["paragraph"]["rich_text"][0]["text"]["content"]
+        );
+        assert_eq!("upload", blocks[6]["file"]["file_upload"]["id"]);
+    }
+
+    #[test]
+    fn bookmark_is_optional() {
+        let entry = NewEntry {
+            add_bookmark: false,
+            tags: None,
+            image: None,
+            archive: None,
+            extra_blocks: vec![],
+            ..entry()
+        };
+
+        assert_eq!(2, entry.into_blocks().len());
+    }
+}
@@ -1,43 +1,130 @@
-use std::str::FromStr;
+use std::{collections::HashMap, str::FromStr};
 
+use chrono::NaiveDate;
 use rusticnotion::{
     ids::PropertyId,
     models::{
-        properties::{Color, External, FileReference, PropertyValue, SelectOption, SelectedValue},
-        text::{RichText, RichTextCommon, Text},
+        block::{CreateBlock, TextAndChildren},
+        properties::{
+            Color, DateOrDateTime, DateValue, External, FileReference, PropertyConfiguration,
+            PropertyValue, SelectOption, SelectedValue,
+        },
+        text::{RichText, RichTextCommon, Text, TextColor},
         Database,
     },
 };
 
+use super::blocks::split_text;
+
+/// An image or other file shown on the page.
+#[derive(Clone, Debug, PartialEq)]
+pub enum PageFile {
+    /// url of a file on an external host
+    External(String),
+    /// id of a file uploaded to notion, see `Notion::upload_file`
+    FileUpload(String),
+}
+
 pub struct NewPage {
     pub database: Database,
     pub name: Option<String>,
     pub url: Option<String>,
-    pub image_url: Option<String>,
+    pub image: Option<PageFile>,
+    /// snapshot of the linked web page
+    pub archive: Option<PageFile>,
     pub tags: Option<Vec<String>>,
+    /// further properties parsed from the message, keyed by property name
+    pub properties: HashMap<String, PropertyValue>,
+    pub date: Option<NaiveDate>,
+    /// name of the date property the date is written to
+    pub date_property: Option<String>,
+    /// the whole message, added as text blocks to the page
+    pub full_text: Option<String>,
+    pub add_bookmark: bool,
+    /// otherwise only tags that already exist in the database are set
+    pub create_unknown_tags: bool,
+    /// appended once the page is created, e.g. the text recognized in images
+    pub extra_blocks: Vec<serde_json::Value>,
+    /// from the template of the user, set unless the message sets them as well
+    pub default_properties: HashMap<String, PropertyValue>,
+    /// sections from the template of the user, placed after the text of the message
+    pub template_blocks: Vec<serde_json::Value>,
 }
 
-impl NewPage {
-    fn empty_id(&self) -> PropertyId {
-        PropertyId::from_str("").unwrap()
+pub(super) const ARCHIVE_PROPERTY: &str = "Archive";
+
+pub(super) fn empty_id() -> PropertyId {
+    PropertyId::from_str("").unwrap()
+}
+
+pub(super) fn text_to_rich_text(content: &str) -> Vec<RichText> {
+    [RichText::Text {
+        text: Text {
+            content: content.to_string(),
+            link: None,
+        },
+        rich_text: RichTextCommon {
+            plain_text: content.to_string(),
+            href: None,
+            annotations: None,
+        },
+    }]
+    .to_vec()
+}
+
+/// `None` if no tag is left, e.g. because unknown tags are not created.
+pub(super) fn tags_property(
+    existing_tags: &[SelectOption],
+    tags: &[String],
+    create_unknown_tags: bool,
+) -> Option<PropertyValue> {
+    let select_values: Vec<SelectedValue> = tags
+        .iter()
+        .map(|tag_name| select_value(existing_tags, tag_name))
+        .filter(|tag| create_unknown_tags || tag.id.is_some())
+        .collect();
+
+    if select_values.is_empty() {
+        return None;
     }
 
+    Some(PropertyValue::MultiSelect {
+        id: empty_id(),
+        multi_select: Some(select_values),
+    })
+}
+
+/// Reuses an existing option of the same name (ignoring case), otherwise notion creates a new one.
+pub(super) fn select_value(existing_options: &[SelectOption], name: &str) -> SelectedValue {
+    let existing_option = existing_options
+        .iter()
+        .find(|option| option.name == name)
+        .or_else(|| {
+            existing_options
+                .iter()
+                .find(|option| option.name.to_lowercase() == name.to_lowercase())
+        });
+
+    match existing_option {
+        Some(existing_option) => SelectedValue {
+            id: Some(existing_option.id.clone()),
+            name: Some(existing_option.name.clone()),
+            color: existing_option.color,
+        },
+        None => SelectedValue {
+            id: None,
+            name: Some(name.to_string()),
+            color: Color::Default,
+        },
+    }
+}
+
+impl NewPage {
     pub fn get_name_property(&self) -> Option<PropertyValue> {
         if let Some(name) = &self.name {
             let name_property: PropertyValue = PropertyValue::Title {
-                id: self.empty_id(),
-                title: [RichText::Text {
-                    text: Text {
-                        content: name.clone(),
-                        link: None,
-                    },
-                    rich_text: RichTextCommon {
-                        plain_text: name.clone(),
-                        href: None,
-                        annotations: None,
-                    },
-                }]
-                .to_vec(),
+                id: empty_id(),
+                title: text_to_rich_text(name),
             };
 
             Some(name_property)
@@ -49,7 +136,7 @@ impl NewPage {
     pub fn get_url_property(&self) -> Option<PropertyValue> {
         if let Some(url) = &self.url {
             let url_property: PropertyValue = PropertyValue::Url {
-                id: self.empty_id(),
+                id: empty_id(),
                 url: Some(url.to_string()),
             };
 
@@ -59,10 +146,66 @@ impl NewPage {
         }
     }
 
+    /// The archive is linked in the `Archive` property if the database has such a url or files property.
+    /// Only for external archives, for uploaded ones see `get_archive_upload` and `links_archive_block`.
+    pub fn get_archive_property(&self) -> Option<(String, PropertyValue)> {
+        let Some(PageFile::External(archive_url)) = &self.archive else {
+            return None;
+        };
+
+        let archive = match self.database.properties.get(ARCHIVE_PROPERTY) {
+            Some(PropertyConfiguration::Url { .. }) => PropertyValue::Url {
+                id: empty_id(),
+                url: Some(archive_url.to_string()),
+            },
+            Some(PropertyConfiguration::Files { .. }) => PropertyValue::Files {
+                id: empty_id(),
+                files: Some(vec![FileReference::External {
+                    name: ARCHIVE_PROPERTY.to_string(),
+                    external: External {
+                        url: archive_url.to_string(),
+                    },
+                }]),
+            },
+            _ => return None,
+        };
+
+        Some((ARCHIVE_PROPERTY.to_string(), archive))
+    }
+
+    /// Id of an archive uploaded to notion, if the database has an `Archive` files property.
+    pub fn get_archive_upload(&self) -> Option<&str> {
+        match (
+            &self.archive,
+            self.database.properties.get(ARCHIVE_PROPERTY),
+        ) {
+            (Some(PageFile::FileUpload(upload_id)), Some(PropertyConfiguration::Files { .. })) => {
+                Some(upload_id)
+            }
+            _ => None,
+        }
+    }
+
+    /// Files uploaded to notion have no lasting url, so an `Archive` url property
+    /// links to the block of the archive on the page instead.
+    pub fn links_archive_block(&self) -> bool {
+        matches!(
+            (
+                &self.archive,
+                self.database.properties.get(ARCHIVE_PROPERTY)
+            ),
+            (
+                Some(PageFile::FileUpload(_)),
+                Some(PropertyConfiguration::Url { .. })
+            )
+        )
+    }
+
+    /// Only for external images, the notion crate does not know file uploads.
     pub fn get_image_property(&self) -> Option<PropertyValue> {
-        if let Some(image_url) = &self.image_url {
+        if let Some(PageFile::External(image_url)) = &self.image {
             let image_property: PropertyValue = PropertyValue::Files {
-                id: self.empty_id(),
+                id: empty_id(),
                 files: Some(
                     [FileReference::External {
                         name: "Image".to_string(),
@@ -80,37 +223,47 @@ impl NewPage {
         }
     }
 
+    pub fn get_date_property(&self) -> Option<(String, PropertyValue)> {
+        let (date, date_property) = (self.date?, self.date_property.as_ref()?);
+
+        match self.database.properties.get(date_property) {
+            Some(PropertyConfiguration::Date { .. }) => Some((
+                date_property.clone(),
+                PropertyValue::Date {
+                    id: empty_id(),
+                    date: Some(DateValue {
+                        start: DateOrDateTime::Date(date),
+                        end: None,
+                        time_zone: None,
+                    }),
+                },
+            )),
+            _ => None,
+        }
+    }
+
     pub fn get_tags_property(&self, existing_tags: Vec<SelectOption>) -> Option<PropertyValue> {
-        if let Some(tags) = &self.tags {
-            let select_values: Vec<SelectedValue> = tags
-                .iter()
-                .map(|tag_name| {
-                    match existing_tags
-                        .iter()
-                        .find(|existing_tag| existing_tag.name == *tag_name)
-                    {
-                        Some(existing_tag) => SelectedValue {
-                            id: Some(existing_tag.id.clone()),
-                            name: Some(existing_tag.name.clone()),
-                            color: existing_tag.color,
-                        },
-                        None => SelectedValue {
-                            id: None,
-                            name: Some(tag_name.to_string()),
-                            color: Color::Default,
-                        },
-                    }
-                })
-                .collect();
+        tags_property(
+            &existing_tags,
+            self.tags.as_deref()?,
+            self.create_unknown_tags,
+        )
+    }
 
-            let tags_property: PropertyValue = PropertyValue::MultiSelect {
-                id: self.empty_id(),
-                multi_select: Some(select_values),
-            };
+    pub fn get_text_blocks(&self) -> Vec<CreateBlock> {
+        let Some(full_text) = &self.full_text else {
+            return vec![];
+        };
 
-            Some(tags_property)
-        } else {
-            None
-        }
+        split_text(full_text)
+            .into_iter()
+            .map(|text| CreateBlock::Paragraph {
+                paragraph: TextAndChildren {
+                    rich_text: text_to_rich_text(&text),
+                    children: None,
+                    color: TextColor::Default,
+                },
+            })
+            .collect()
     }
 }
@@ -0,0 +1,175 @@
+use std::{collections::HashMap, fmt};
+
+use chrono::NaiveDate;
+use rusticnotion::models::{
+    properties::{DateOrDateTime, DateValue, PropertyConfiguration, PropertyValue},
+    Database,
+};
+
+use super::new_page::{empty_id, select_value, text_to_rich_text};
+use crate::text::parse_date;
+
+#[derive(Debug, PartialEq)]
+pub struct PropertyError {
+    pub property: String,
+    pub message: String,
+}
+
+impl fmt::Display for PropertyError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Could not set {}: {}", self.property, self.message)
+    }
+}
+
+/// Maps `key: value` pairs from a message onto the properties of the database.
+/// Keys that are not properties of the database are ignored,
+/// values that do not fit the property type are returned as errors.
+pub fn parse_property_values(
+    database: &Database,
+    raw_properties: &[(String, String)],
+    today: NaiveDate,
+) -> (HashMap<String, PropertyValue>, Vec<PropertyError>) {
+    let mut properties = HashMap::new();
+    let mut errors = vec![];
+
+    for (key, value) in raw_properties {
+        let Some((name, configuration)) = find_property(database, key) else {
+            continue;
+        };
+
+        match property_value(configuration, value, today) {
+            Ok(property) => {
+                properties.insert(name.clone(), property);
+            }
+            Err(message) => errors.push(PropertyError {
+                property: name.clone(),
+                message,
+            }),
+        }
+    }
+
+    (properties, errors)
+}
+
+fn normalize_key(key: &str) -> String {
+    key.to_lowercase()
+        .replace(['_', '-'], " ")
+        .split_whitespace()
+        .collect::<Vec<&str>>()
+        .join(" ")
+}
+
+/// The property the key from a message refers to, ignoring case, spaces, `_` and `-`.
+pub fn find_property<'a>(
+    database: &'a Database,
+    key: &str,
+) -> Option<(&'a String, &'a PropertyConfiguration)> {
+    let key = normalize_key(key);
+
+    database
+        .properties
+        .iter()
+        .find(|(name, _)| normalize_key(name) == key)
+}
+
+fn property_value(
+    configuration: &PropertyConfiguration,
+    value: &str,
+    today: NaiveDate,
+) -> Result<PropertyValue, String> {
+    match configuration {
+        PropertyConfiguration::Text { .. } => Ok(PropertyValue::Text {
+            id: empty_id(),
+            rich_text: text_to_rich_text(value),
+        }),
+        PropertyConfiguration::Url { .. } => Ok(PropertyValue::Url {
+            id: empty_id(),
+            url: Some(value.to_string()),
+        }),
+        PropertyConfiguration::Number { .. } => Ok(PropertyValue::Number {
+            id: empty_id(),
+            number: Some(parse_number(value)?),
+        }),
+        PropertyConfiguration::Checkbox { .. } => Ok(PropertyValue::Checkbox {
+            id: empty_id(),
+            checkbox: parse_checkbox(value)?,
+        }),
+        PropertyConfiguration::Date { .. } => Ok(PropertyValue::Date {
+            id: empty_id(),
+            date: Some(DateValue {
+                start: DateOrDateTime::Date(parse_date(value, today).ok_or_else(|| {
+                    format!("expected a date like 2026-11-03 or \"next friday\", got \"{value}\"")
+                })?),
+                end: None,
+                time_zone: None,
+            }),
+        }),
+        PropertyConfiguration::Select { select, .. } => Ok(PropertyValue::Select {
+            id: empty_id(),
+            select: Some(select_value(&select.options, value)),
+        }),
+        PropertyConfiguration::MultiSelect { multi_select, .. } => Ok(PropertyValue::MultiSelect {
+            id: empty_id(),
+            multi_select: Some(
+                value
+                    .split(',')
+                    .map(str::trim)
+                    .filter(|option| !option.is_empty())
+                    .map(|option| select_value(&multi_select.options, option))
+                    .collect(),
+            ),
+        }),
+        _ => Err("this property type cannot be set from a message".to_string()),
+    }
+}
+
+fn parse_number(value: &str) -> Result<serde_json::Number, String> {
+    let error = || format!("expected a number, got \"{value}\"");
+    let value = value.trim().replace(',', ".");
+
+    if let Ok(integer) = value.parse::<i64>() {
+        return Ok(integer.into());
+    }
+
+    value
+        .parse::<f64>()
+        .ok()
+        .and_then(serde_json::Number::from_f64)
+        .ok_or_else(error)
+}
+
+fn parse_checkbox(value: &str) -> Result<bool, String> {
+    match value.trim().to_lowercase().as_str() {
+        "yes" | "y" | "true" | "x" | "1" | "✓" | "✅" => Ok(true),
+        "no" | "n" | "false" | "0" | "-" => Ok(false),
+        _ => Err(format!("expected yes or no, got \"{value}\"")),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn numbers() {
+        assert_eq!(Ok(4.into()), parse_number("4"));
+        assert_eq!(
+            serde_json::Number::from_f64(3.5).ok_or(String::new()),
+            parse_number("3,5")
+        );
+        assert!(parse_number("four").is_err());
+    }
+
+    #[test]
+    fn checkboxes() {
+        assert_eq!(Ok(true), parse_checkbox("Yes"));
+        assert_eq!(Ok(false), parse_checkbox("no"));
+        assert!(parse_checkbox("maybe").is_err());
+    }
+
+    #[test]
+    fn keys_are_matched_loosely() {
+        assert_eq!(normalize_key("Due date"), normalize_key("due_date"));
+        assert_eq!(normalize_key("Due  Date"), normalize_key("due-date"));
+    }
+}
@@ -0,0 +1,137 @@
+//! Queries of the database endpoint, which the notion crate does not cover with filters.
+
+use std::collections::HashMap;
+
+use rusticnotion::models::{properties::PropertyConfiguration, Database};
+use serde::Deserialize;
+use serde_json::{json, Value};
+
+/// tags matching a search are combined into one filter, so it has to stay small
+const MAX_SEARCHED_TAGS: usize = 10;
+
+/// A page found by `Notion::query_database`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct FoundPage {
+    pub id: String,
+    pub title: String,
+    pub url: String,
+    pub tags: Vec<String>,
+}
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct QueryResults {
+    pub pages: Vec<FoundPage>,
+    /// passed to the next query to get the following pages
+    pub next_cursor: Option<String>,
+}
+
+#[derive(Deserialize)]
+pub(super) struct QueryResponse {
+    results: Vec<QueriedPage>,
+    next_cursor: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct QueriedPage {
+    id: String,
+    url: String,
+    properties: HashMap<String, Value>,
+}
+
+impl From<QueryResponse> for QueryResults {
+    fn from(response: QueryResponse) -> Self {
+        let pages = response
+            .results
+            .into_iter()
+            .map(|page| {
+                let text = |values: &Value, field: &str| -> Vec<String> {
+                    values
+                        .as_array()
+                        .into_iter()
+                        .flatten()
+                        .filter_map(|value| value[field].as_str().map(str::to_string))
+                        .collect()
+                };
+
+                FoundPage {
+                    title: page
+                        .properties
+                        .get("Name")
+                        .map(|name| text(&name["title"], "plain_text").concat())
+                        .unwrap_or_default(),
+                    tags: page
+                        .properties
+                        .get("Tags")
+                        .map(|tags| text(&tags["multi_select"], "name"))
+                        .unwrap_or_default(),
+                    id: page.id,
+                    url: page.url,
+                }
+            })
+            .collect();
+
+        QueryResults {
+            pages,
+            next_cursor: response.next_cursor,
+        }
+    }
+}
+
+/// The latest pages first.
+pub fn recent_query() -> Value {
+    json!({ "sorts": [{ "timestamp": "created_time", "direction": "descending" }] })
+}
+
+/// Pages whose title or tags contain the text, newest first.
+pub fn search_query(database: &Database, text: &str) -> Value {
+    let mut filters = vec![json!({ "property": "Name", "title": { "contains": text } })];
+
+    // multi selects only match whole options, so the options containing the text are searched
+    if let Some(PropertyConfiguration::MultiSelect { multi_select, .. }) =
+        database.properties.get("Tags")
+    {
+        filters.extend(
+            multi_select
+                .options
+                .iter()
+                .filter(|option| option.name.to_lowercase().contains(&text.to_lowercase()))
+                .take(MAX_SEARCHED_TAGS)
+                .map(|option| {
+                    json!({ "property": "Tags", "multi_select": { "contains": option.name } })
+                }),
+        );
+    }
+
+    json!({
+        "filter": { "or": filters },
+        "sorts": [{ "timestamp": "created_time", "direction": "descending" }],
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reads_found_pages() {
+        let response: QueryResponse = serde_json::from_value(json!({
+            "results": [{
+                "id": "page-1",
+                "url": "https://www.notion.so/Rust-page1",
+                "properties": {
+                    "Name": { "title": [{ "plain_text": "Rust " }, { "plain_text": "book" }] },
+                    "Tags": { "multi_select": [{ "name": "read" }] }
+                }
+            }],
+            "next_cursor": "page-2",
+            "has_more": true
+        }))
+        .unwrap();
+
+        let results = QueryResults::from(response);
+
+        assert_eq!("Rust book", results.pages[0].title);
+        assert_eq!(vec!["read".to_string()], results.pages[0].tags);
+        assert_eq!(Some("page-2".to_string()), results.next_cursor);
+    }
+}
@@ -0,0 +1,103 @@
+use std::{
+    collections::HashMap,
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+
+/// Notion allows an average of three requests per second per integration
+const NOTION_REQUEST_INTERVAL: Duration = Duration::from_millis(334);
+
+/// Spaces out the requests of each integration token,
+/// so bursts of messages are queued instead of running into 429 responses.
+pub struct RateLimiter {
+    interval: Duration,
+    next_slots: Mutex<HashMap<String, Instant>>,
+}
+
+impl Default for RateLimiter {
+    fn default() -> Self {
+        Self::new(NOTION_REQUEST_INTERVAL)
+    }
+}
+
+impl RateLimiter {
+    pub fn new(interval: Duration) -> Self {
+        RateLimiter {
+            interval,
+            next_slots: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Waits until the token may send its next request.
+    pub async fn acquire(&self, token: &str) {
+        let wait = {
+            let mut next_slots = self.next_slots.lock().unwrap();
+            let now = Instant::now();
+
+            let slot = next_slots
+                .get(token)
+                .copied()
+                .map_or(now, |slot| slot.max(now));
+            // tokens whose slot has passed would not wait anyway, so the map only holds active ones
+            next_slots.retain(|_, next_slot| *next_slot > now);
+            next_slots.insert(token.to_string(), slot + self.interval);
+
+            slot - now
+        };
+
+        if !wait.is_zero() {
+            tokio::time::sleep(wait).await;
+        }
+    }
+
+    /// Holds back all requests of the token, e.g. for the Retry-After of a 429 response.
+    pub fn pause(&self, token: &str, duration: Duration) {
+        let mut next_slots = self.next_slots.lock().unwrap();
+        let resume_at = Instant::now() + duration;
+
+        let slot = next_slots.entry(token.to_string()).or_insert(resume_at);
+        *slot = (*slot).max(resume_at);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn requests_of_a_token_are_spaced() {
+        let limiter = RateLimiter::new(Duration::from_millis(50));
+        let start = Instant::now();
+
+        limiter.acquire("a").await;
+        limiter.acquire("b").await;
+        assert!(start.elapsed() < Duration::from_millis(50));
+
+        limiter.acquire("a").await;
+        limiter.acquire("a").await;
+        assert!(start.elapsed() >= Duration::from_millis(100));
+    }
+
+    #[tokio::test]
+    async fn idle_tokens_are_dropped() {
+        let limiter = RateLimiter::new(Duration::from_millis(10));
+
+        limiter.acquire("a").await;
+        tokio::time::sleep(Duration::from_millis(20)).await;
+        limiter.acquire("b").await;
+
+        let next_slots = limiter.next_slots.lock().unwrap();
+        assert_eq!(vec!["b"], next_slots.keys().collect::<Vec<_>>());
+    }
+
+    #[tokio::test]
+    async fn pause_delays_next_request() {
+        let limiter = RateLimiter::new(Duration::from_millis(1));
+        let start = Instant::now();
+
+        limiter.pause("a", Duration::from_millis(60));
+        limiter.acquire("a").await;
+
+        assert!(start.elapsed() >= Duration::from_millis(60));
+    }
+}
@@ -0,0 +1,65 @@
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+};
+
+use super::{JournalPages, Notion, RateLimiter, SchemaCache};
+use crate::{db::UserDetails, error::Result};
+
+/// One notion client per user, so their HTTP connections are reused across messages.
+pub struct NotionClients {
+    rate_limiter: Arc<RateLimiter>,
+    schema_cache: Arc<SchemaCache>,
+    journal_pages: Arc<JournalPages>,
+    clients: Mutex<HashMap<String, Arc<Notion>>>,
+}
+
+impl Default for NotionClients {
+    fn default() -> Self {
+        Self::new(
+            Arc::new(RateLimiter::default()),
+            Arc::new(SchemaCache::default()),
+            Arc::new(JournalPages::default()),
+        )
+    }
+}
+
+impl NotionClients {
+    pub fn new(
+        rate_limiter: Arc<RateLimiter>,
+        schema_cache: Arc<SchemaCache>,
+        journal_pages: Arc<JournalPages>,
+    ) -> Self {
+        NotionClients {
+            rate_limiter,
+            schema_cache,
+            journal_pages,
+            clients: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Returns the user's client, a new one is created if the token changed.
+    pub fn get(&self, user_details: &UserDetails) -> Result<Arc<Notion>> {
+        let mut clients = self.clients.lock().unwrap();
+
+        if let Some(client) = clients.get(&user_details.user_id) {
+            if client.token() == user_details.integration_token {
+                return Ok(client.clone());
+            }
+        }
+
+        let client = Arc::new(Notion::new(
+            user_details.integration_token.clone(),
+            self.rate_limiter.clone(),
+            self.schema_cache.clone(),
+            self.journal_pages.clone(),
+        )?);
+        clients.insert(user_details.user_id.clone(), client.clone());
+
+        Ok(client)
+    }
+
+    pub fn evict(&self, user_id: &str) {
+        self.clients.lock().unwrap().remove(user_id);
+    }
+}
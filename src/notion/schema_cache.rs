@@ -0,0 +1,94 @@
+use std::{
+    collections::HashMap,
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+
+use rusticnotion::models::Database;
+
+const SCHEMA_TTL: Duration = Duration::from_secs(10 * 60);
+
+/// Database schemas keyed by integration token and database id,
+/// so a capture does not need to search notion for its database every time.
+pub struct SchemaCache {
+    ttl: Duration,
+    entries: Mutex<HashMap<(String, String), (Instant, Database)>>,
+}
+
+impl Default for SchemaCache {
+    fn default() -> Self {
+        Self::new(SCHEMA_TTL)
+    }
+}
+
+impl SchemaCache {
+    pub fn new(ttl: Duration) -> Self {
+        SchemaCache {
+            ttl,
+            entries: Mutex::new(HashMap::new()),
+        }
+    }
+
+    pub fn get(&self, token: &str, database_id: &str) -> Option<Database> {
+        let mut entries = self.entries.lock().unwrap();
+        let key = (token.to_string(), database_id.to_string());
+
+        match entries.get(&key) {
+            Some((fetched_at, database)) if fetched_at.elapsed() < self.ttl => {
+                Some(database.clone())
+            }
+            Some(_) => {
+                entries.remove(&key);
+                None
+            }
+            None => None,
+        }
+    }
+
+    pub fn insert(&self, token: &str, database_id: &str, database: Database) {
+        self.entries.lock().unwrap().insert(
+            (token.to_string(), database_id.to_string()),
+            (Instant::now(), database),
+        );
+    }
+
+    pub fn invalidate(&self, token: &str, database_id: &str) {
+        self.entries
+            .lock()
+            .unwrap()
+            .remove(&(token.to_string(), database_id.to_string()));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn database() -> Database {
+        serde_json::from_value(serde_json::json!({
+            "object": "database",
+            "id": "1",
+            "created_time": "2024-07-04T00:00:00.000Z",
+            "last_edited_time": "2024-07-04T00:00:00.000Z",
+            "title": [],
+            "properties": {}
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn entries_expire_and_can_be_invalidated() {
+        let cache = SchemaCache::new(Duration::from_millis(50));
+
+        cache.insert("token", "1", database());
+        assert!(cache.get("token", "1").is_some());
+        assert!(cache.get("other token", "1").is_none());
+
+        cache.invalidate("token", "1");
+        assert!(cache.get("token", "1").is_none());
+
+        cache.insert("token", "1", database());
+        std::thread::sleep(Duration::from_millis(60));
+        assert!(cache.get("token", "1").is_none());
+    }
+}
@@ -0,0 +1,199 @@
+use std::collections::BTreeMap;
+
+use serde::{de::Error, Deserialize, Deserializer, Serialize};
+
+use super::ContentBlock;
+
+/// Sections and default property values added to every new page of a user, e.g.
+/// `{"properties": {"Status": "Inbox"}, "blocks": [{"type": "todo", "text": "Read {title}"}]}`
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct PageTemplate {
+    /// `key: value` pairs like in a message, values from the message win
+    #[serde(default)]
+    pub properties: BTreeMap<String, String>,
+    #[serde(default)]
+    pub blocks: Vec<TemplateBlock>,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case", deny_unknown_fields)]
+pub enum TemplateBlock {
+    Heading {
+        /// 1 to 3 like in notion
+        #[serde(default = "default_heading_level", deserialize_with = "heading_level")]
+        level: u8,
+        text: String,
+    },
+    Paragraph {
+        text: String,
+    },
+    Todo {
+        text: String,
+        #[serde(default)]
+        checked: bool,
+    },
+    BulletedListItem {
+        text: String,
+    },
+    NumberedListItem {
+        text: String,
+    },
+    Quote {
+        text: String,
+    },
+    Divider,
+}
+
+fn default_heading_level() -> u8 {
+    2
+}
+
+fn heading_level<'de, D: Deserializer<'de>>(deserializer: D) -> Result<u8, D::Error> {
+    let level = u8::deserialize(deserializer)?;
+
+    if (1..=3).contains(&level) {
+        Ok(level)
+    } else {
+        Err(D::Error::custom(format!(
+            "heading level {level} does not exist, use 1, 2 or 3"
+        )))
+    }
+}
+
+/// Values for `{title}`, `{url}`, `{date}` and `{tags}` in a template.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Placeholders {
+    pub title: String,
+    pub url: String,
+    pub date: String,
+    /// comma separated
+    pub tags: String,
+}
+
+impl Placeholders {
+    fn fill(&self, text: &str) -> String {
+        text.replace("{title}", &self.title)
+            .replace("{url}", &self.url)
+            .replace("{date}", &self.date)
+            .replace("{tags}", &self.tags)
+    }
+}
+
+impl PageTemplate {
+    pub fn properties(&self, placeholders: &Placeholders) -> Vec<(String, String)> {
+        self.properties
+            .iter()
+            .map(|(key, value)| (key.clone(), placeholders.fill(value)))
+            .collect()
+    }
+
+    pub fn blocks(&self, placeholders: &Placeholders) -> Vec<ContentBlock> {
+        self.blocks
+            .iter()
+            .map(|block| match block {
+                TemplateBlock::Heading { level, text } => ContentBlock::Heading {
+                    level: *level,
+                    text: placeholders.fill(text),
+                },
+                TemplateBlock::Paragraph { text } => {
+                    ContentBlock::Paragraph(placeholders.fill(text))
+                }
+                TemplateBlock::Todo { text, checked } => ContentBlock::ToDo {
+                    text: placeholders.fill(text),
+                    checked: *checked,
+                },
+                TemplateBlock::BulletedListItem { text } => {
+                    ContentBlock::BulletedListItem(placeholders.fill(text))
+                }
+                TemplateBlock::NumberedListItem { text } => {
+                    ContentBlock::NumberedListItem(placeholders.fill(text))
+                }
+                TemplateBlock::Quote { text } => ContentBlock::Quote(placeholders.fill(text)),
+                TemplateBlock::Divider => ContentBlock::Divider,
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn fills_placeholders() {
+        let template: PageTemplate = serde_json::from_str(
+            r#"{
+                "properties": { "Status": "Inbox", "Source": "{url}" },
+                "blocks": [
+                    { "type": "heading", "text": "Notes on {title}" },
+                    { "type": "todo", "text": "Read by {date}" },
+                    { "type": "divider" }
+                ]
+            }"#,
+        )
+        .unwrap();
+        let placeholders = Placeholders {
+            title: "Rust".to_string(),
+            url: "https://www.rust-lang.org".to_string(),
+            date: "2024-06-01".to_string(),
+            tags: String::new(),
+        };
+
+        assert_eq!(
+            vec![
+                (
+                    "Source".to_string(),
+                    "https://www.rust-lang.org".to_string()
+                ),
+                ("Status".to_string(), "Inbox".to_string())
+            ],
+            template.properties(&placeholders)
+        );
+        assert_eq!(
+            vec![
+                ContentBlock::Heading {
+                    level: 2,
+                    text: "Notes on Rust".to_string()
+                },
+                ContentBlock::ToDo {
+                    text: "Read by 2024-06-01".to_string(),
+                    checked: false
+                },
+                ContentBlock::Divider
+            ],
+            template.blocks(&placeholders)
+        );
+    }
+
+    #[test]
+    fn heading_levels_are_checked() {
+        let heading = |level: u8| {
+            serde_json::from_str::<PageTemplate>(&format!(
+                r#"{{ "blocks": [{{ "type": "heading", "level": {level}, "text": "Notes" }}] }}"#
+            ))
+        };
+
+        assert!(heading(0).is_err());
+        assert!(heading(4).is_err());
+        assert_eq!(
+            vec![TemplateBlock::Heading {
+                level: 3,
+                text: "Notes".to_string()
+            }],
+            heading(3).unwrap().blocks
+        );
+    }
+
+    #[test]
+    fn unknown_blocks_are_rejected() {
+        assert!(
+            serde_json::from_str::<PageTemplate>(r#"{ "blocks": [{ "type": "video" }] }"#).is_err()
+        );
+        assert!(serde_json::from_str::<PageTemplate>(r#"{ "pages": [] }"#).is_err());
+        assert_eq!(
+            PageTemplate::default(),
+            serde_json::from_str::<PageTemplate>("{}").unwrap()
+        );
+    }
+}
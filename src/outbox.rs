@@ -0,0 +1,117 @@
+use std::{sync::Arc, time::Duration};
+
+use chrono::Utc;
+use teloxide::{prelude::*, types::MessageId};
+
+use crate::{
+    db::{Database, OutboxEntry},
+    handlers::message::{add_capture_to_notion, Capture},
+    notion::NotionClients,
+    storage::ImageHost,
+};
+
+pub const OUTBOX_FIRST_RETRY_SECONDS: i64 = 30;
+const OUTBOX_MAX_RETRY_SECONDS: i64 = 60 * 60;
+const OUTBOX_MAX_ATTEMPTS: u32 = 10;
+const OUTBOX_POLL_INTERVAL: Duration = Duration::from_secs(10);
+
+/// Retries captures that failed because notion or the image host was unavailable,
+/// with exponential backoff between the attempts.
+pub async fn run_outbox(
+    bot: Bot,
+    db: Arc<Database>,
+    image_host: Arc<ImageHost>,
+    notion_clients: Arc<NotionClients>,
+) {
+    loop {
+        tokio::time::sleep(OUTBOX_POLL_INTERVAL).await;
+
+        let entries = match db.get_due_outbox_entries(Utc::now().timestamp()) {
+            Ok(entries) => entries,
+            Err(err) => {
+                log::error!("could not read outbox: {err}");
+                continue;
+            }
+        };
+
+        for entry in entries {
+            if let Err(err) = retry_entry(&bot, &db, &image_host, &notion_clients, &entry).await {
+                log::error!("could not process outbox entry {}: {err}", entry.id);
+            }
+        }
+    }
+}
+
+fn next_attempt_at(attempts: u32) -> i64 {
+    let delay = OUTBOX_FIRST_RETRY_SECONDS.saturating_mul(1 << attempts.min(16));
+
+    Utc::now().timestamp() + delay.min(OUTBOX_MAX_RETRY_SECONDS)
+}
+
+async fn retry_entry(
+    bot: &Bot,
+    db: &Database,
+    image_host: &ImageHost,
+    notion_clients: &NotionClients,
+    entry: &OutboxEntry,
+) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    let capture: Capture = match serde_json::from_str(&entry.capture) {
+        Ok(capture) => capture,
+        Err(err) => {
+            log::error!("dropping unreadable outbox entry {}: {err}", entry.id);
+            db.delete_outbox_entry(entry.id)?;
+            return Ok(());
+        }
+    };
+
+    let result = add_capture_to_notion(
+        bot,
+        db,
+        image_host,
+        notion_clients,
+        &capture,
+        &entry.target,
+        true,
+    )
+    .await;
+
+    match result {
+        Ok(reply) => {
+            log::info!("created page for outbox entry {}", entry.id);
+            db.delete_outbox_entry(entry.id)?;
+
+            // the page exists, so a failed reply is not retried
+            if let Err(err) = reply.send(bot).await {
+                log::error!("could not reply for outbox entry {}: {err}", entry.id);
+            }
+        }
+        Err(err) if err.is_retryable() && entry.attempts + 1 < OUTBOX_MAX_ATTEMPTS => {
+            log::warn!(
+                "retry {} of outbox entry {} failed: {err}",
+                entry.attempts + 1,
+                entry.id
+            );
+            db.reschedule_outbox_entry(
+                entry.id,
+                next_attempt_at(entry.attempts + 1),
+                &err.to_string(),
+            )?;
+        }
+        Err(err) => {
+            log::error!("giving up on outbox entry {}: {err}", entry.id);
+            db.delete_outbox_entry(entry.id)?;
+
+            bot.send_message(
+                ChatId(capture.chat_id),
+                format!(
+                    "I gave up saving this message to Notion.\n{}",
+                    err.user_message()
+                ),
+            )
+            .reply_to_message_id(MessageId(capture.message_id))
+            .await?;
+        }
+    }
+
+    Ok(())
+}
@@ -0,0 +1,52 @@
+use async_trait::async_trait;
+use reqwest::multipart;
+use serde::Deserialize;
+
+use super::{Image, ImageStorage};
+use crate::error::{BotError, Result};
+
+pub struct ImgPush {
+    url: String,
+}
+
+#[derive(Deserialize)]
+struct ImgPushResponse {
+    filename: String,
+}
+
+impl ImgPush {
+    pub fn new(url: String) -> Self {
+        ImgPush { url }
+    }
+}
+
+#[async_trait]
+impl ImageStorage for ImgPush {
+    async fn upload(&self, image: &Image) -> Result<String> {
+        let file = multipart::Part::bytes(image.content.clone())
+            .file_name(image.filename.clone())
+            .mime_str(&image.content_type)
+            .map_err(|err| BotError::ImageHostDown(err.to_string()))?;
+
+        let client = reqwest::Client::new();
+        let resp = client
+            .post(&self.url)
+            .multipart(multipart::Form::new().part("file", file))
+            .send()
+            .await
+            .map_err(|err| BotError::ImageHostDown(err.without_url().to_string()))?;
+
+        if let Err(err) = resp.error_for_status_ref() {
+            return Err(BotError::ImageHostDown(err.without_url().to_string()));
+        }
+
+        if let Ok(data) = resp.json::<ImgPushResponse>().await {
+            let image_url = format!("{}/{}", self.url, data.filename);
+            Ok(image_url)
+        } else {
+            Err(BotError::ImageHostDown(
+                "unexpected response when uploading".to_string(),
+            ))
+        }
+    }
+}
@@ -0,0 +1,66 @@
+use std::path::PathBuf;
+
+use async_trait::async_trait;
+
+use super::{content_name, Image, ImageStorage};
+use crate::error::{BotError, Result};
+
+/// Stores images in a directory that is served by a static file server.
+pub struct LocalStorage {
+    directory: PathBuf,
+    /// url the directory is served at
+    base_url: String,
+}
+
+impl LocalStorage {
+    pub fn new(directory: PathBuf, base_url: String) -> Self {
+        LocalStorage {
+            directory,
+            base_url: base_url.trim_end_matches('/').to_string(),
+        }
+    }
+}
+
+#[async_trait]
+impl ImageStorage for LocalStorage {
+    async fn upload(&self, image: &Image) -> Result<String> {
+        let name = content_name(image);
+
+        tokio::fs::create_dir_all(&self.directory)
+            .await
+            .map_err(|err| BotError::ImageHostDown(err.to_string()))?;
+        tokio::fs::write(self.directory.join(&name), &image.content)
+            .await
+            .map_err(|err| BotError::ImageHostDown(err.to_string()))?;
+
+        Ok(format!("{}/{name}", self.base_url))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn stores_image_in_directory() {
+        let directory = tempfile::tempdir().unwrap();
+        let storage = LocalStorage::new(
+            directory.path().to_path_buf(),
+            "https://img.example.com/".to_string(),
+        );
+        let image = Image {
+            filename: "file_1.jpg".to_string(),
+            content_type: "image/jpeg".to_string(),
+            content: vec![1, 2, 3],
+        };
+
+        let url = storage.upload(&image).await.unwrap();
+
+        let name = url.strip_prefix("https://img.example.com/").unwrap();
+        assert!(name.ends_with(".jpg"));
+        assert_eq!(
+            vec![1, 2, 3],
+            std::fs::read(directory.path().join(name)).unwrap()
+        );
+    }
+}
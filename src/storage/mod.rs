@@ -0,0 +1,110 @@
+use std::env;
+
+use async_trait::async_trait;
+use sha2::{Digest, Sha256};
+
+use crate::error::Result;
+
+mod img_push;
+mod local;
+mod ocr;
+mod processing;
+mod s3;
+
+pub use img_push::ImgPush;
+pub use local::LocalStorage;
+pub use ocr::Ocr;
+pub use processing::ImageProcessing;
+pub use s3::S3Storage;
+
+/// Somewhere images and other files, e.g. page snapshots, can be stored and linked from notion.
+#[async_trait]
+pub trait ImageStorage: Send + Sync {
+    /// Stores the file and returns the url it is served at.
+    async fn upload(&self, image: &Image) -> Result<String>;
+}
+
+/// How the images of captures are processed and where they are stored.
+pub struct ImageHost {
+    pub processing: ImageProcessing,
+    /// text in images is only recognized if OCR is set up
+    pub ocr: Option<Ocr>,
+    pub target: ImageTarget,
+}
+
+pub enum ImageTarget {
+    /// stored by one of the storage backends and linked from notion
+    External(Box<dyn ImageStorage>),
+    /// uploaded to notion itself, so they do not depend on another service
+    Notion,
+}
+
+impl ImageHost {
+    pub fn from_env() -> std::result::Result<Self, String> {
+        Ok(ImageHost {
+            processing: ImageProcessing::from_env()?,
+            ocr: Ocr::from_env(),
+            target: ImageTarget::from_env()?,
+        })
+    }
+}
+
+impl ImageTarget {
+    /// Reads `IMAGE_HOST`, one of `img-push` (default), `s3`, `local` or `notion`.
+    pub fn from_env() -> std::result::Result<Self, String> {
+        match env::var("IMAGE_HOST").as_deref() {
+            Ok("img-push") | Err(_) => {
+                let img_push_url = env_var("IMG_PUSH_URL")?;
+                Ok(ImageTarget::External(Box::new(ImgPush::new(img_push_url))))
+            }
+            Ok("s3") => Ok(ImageTarget::External(Box::new(S3Storage::from_env()?))),
+            Ok("local") => Ok(ImageTarget::External(Box::new(LocalStorage::new(
+                env_var("LOCAL_IMAGE_DIR")?.into(),
+                env_var("LOCAL_IMAGE_URL")?,
+            )))),
+            Ok("notion") => Ok(ImageTarget::Notion),
+            Ok(other) => Err(format!(
+                "Unknown IMAGE_HOST \"{other}\", use \"img-push\", \"s3\", \"local\" or \"notion\""
+            )),
+        }
+    }
+}
+
+fn env_var(name: &str) -> std::result::Result<String, String> {
+    env::var(name).map_err(|_| format!("{name} not set"))
+}
+
+/// An image downloaded from telegram or another file to be stored.
+pub struct Image {
+    pub filename: String,
+    pub content_type: String,
+    pub content: Vec<u8>,
+}
+
+/// A name derived from the content, so the same image is only stored once.
+fn content_name(image: &Image) -> String {
+    // the first 128 bits of the hash, which stays the same across builds
+    let hash = format!("{:x}", Sha256::digest(&image.content));
+    let hash = &hash[..32];
+
+    match image.filename.rsplit_once('.') {
+        Some((_, extension)) => format!("{hash}.{extension}"),
+        None => hash.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn content_name_is_sha256_of_content() {
+        let image = Image {
+            filename: "file_1.jpg".to_string(),
+            content_type: "image/jpeg".to_string(),
+            content: b"abc".to_vec(),
+        };
+
+        assert_eq!("ba7816bf8f01cfea414140de5dae2223.jpg", content_name(&image));
+    }
+}
@@ -0,0 +1,105 @@
+use std::{process::Stdio, time::Duration};
+
+use tokio::{io::AsyncWriteExt, process::Command};
+
+use super::{env_var, Image};
+use crate::error::{BotError, Result};
+
+const OCR_TIMEOUT: Duration = Duration::from_secs(60);
+
+/// Recognizes text in images with a tesseract compatible command line tool.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Ocr {
+    command: String,
+    /// e.g. `eng+deu`, tesseract's default is used otherwise
+    languages: Option<String>,
+}
+
+impl Ocr {
+    /// Reads `OCR_COMMAND` and optionally `OCR_LANGUAGES`, OCR is disabled without a command.
+    pub fn from_env() -> Option<Self> {
+        Some(Ocr {
+            command: env_var("OCR_COMMAND")
+                .ok()
+                .filter(|command| !command.is_empty())?,
+            languages: env_var("OCR_LANGUAGES").ok(),
+        })
+    }
+
+    pub async fn recognize(&self, image: &Image) -> Result<String> {
+        let mut command = Command::new(&self.command);
+        command
+            .args(["stdin", "stdout"])
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .kill_on_drop(true);
+        if let Some(languages) = &self.languages {
+            command.args(["-l", languages]);
+        }
+
+        let ocr_error =
+            |err: std::io::Error| BotError::ImageProcessing(format!("OCR failed: {err}"));
+        let mut child = command.spawn().map_err(ocr_error)?;
+
+        let mut stdin = child.stdin.take().unwrap();
+        let write = async move {
+            stdin.write_all(&image.content).await?;
+            // closing stdin lets tesseract start
+            drop(stdin);
+            Ok::<_, std::io::Error>(())
+        };
+
+        // a tool that stops reading would block the write once the pipe is full,
+        // so it is covered by the timeout as well, the tool is killed when it runs out
+        let (written, output) = tokio::time::timeout(OCR_TIMEOUT, async {
+            tokio::join!(write, child.wait_with_output())
+        })
+        .await
+        .map_err(|_| BotError::ImageProcessing("OCR timed out".to_string()))?;
+        let output = output.map_err(ocr_error)?;
+
+        if !output.status.success() {
+            return Err(BotError::ImageProcessing(format!(
+                "OCR failed: {}",
+                String::from_utf8_lossy(&output.stderr).trim()
+            )));
+        }
+        written.map_err(ocr_error)?;
+
+        Ok(clean_text(&String::from_utf8_lossy(&output.stdout)))
+    }
+}
+
+/// Trims the lines and drops the empty lines tesseract adds between blocks of text,
+/// paragraphs stay separated by one empty line.
+fn clean_text(text: &str) -> String {
+    let mut paragraphs: Vec<Vec<&str>> = vec![vec![]];
+
+    for line in text.lines().map(str::trim) {
+        match (line.is_empty(), paragraphs.last_mut()) {
+            (false, Some(paragraph)) => paragraph.push(line),
+            (true, Some(paragraph)) if !paragraph.is_empty() => paragraphs.push(vec![]),
+            _ => {}
+        }
+    }
+
+    paragraphs
+        .iter()
+        .filter(|paragraph| !paragraph.is_empty())
+        .map(|paragraph| paragraph.join("\n"))
+        .collect::<Vec<String>>()
+        .join("\n\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn cleans_recognized_text() {
+        let text = "  Meeting notes \n\n\n\nAgenda\n 1. Budget\n\n\u{c}";
+
+        assert_eq!("Meeting notes\n\nAgenda\n1. Budget", clean_text(text));
+    }
+}
@@ -0,0 +1,285 @@
+use std::io::Cursor;
+
+use image::{
+    codecs::{jpeg::JpegEncoder, webp::WebPEncoder},
+    imageops::FilterType,
+    DynamicImage, ImageDecoder, ImageError, ImageFormat, ImageReader,
+};
+
+use super::{env_var, Image};
+use crate::error::{BotError, Result};
+
+const DEFAULT_MAX_DIMENSION: u32 = 2048;
+const DEFAULT_QUALITY: u8 = 85;
+
+/// Applied to every image before it is uploaded.
+/// Re-encoding drops all metadata, e.g. the location in the EXIF data of phone photos.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ImageProcessing {
+    /// larger images are scaled down to fit
+    pub max_dimension: u32,
+    /// jpeg quality from 1 to 100
+    pub quality: u8,
+    /// WebP images are converted, otherwise they stay WebP. HEIC is always converted.
+    pub convert_to_jpeg: bool,
+}
+
+impl Default for ImageProcessing {
+    fn default() -> Self {
+        ImageProcessing {
+            max_dimension: DEFAULT_MAX_DIMENSION,
+            quality: DEFAULT_QUALITY,
+            convert_to_jpeg: true,
+        }
+    }
+}
+
+impl ImageProcessing {
+    /// Reads `IMAGE_MAX_DIMENSION`, `IMAGE_QUALITY` and `IMAGE_CONVERT_TO_JPEG`, all optional.
+    pub fn from_env() -> std::result::Result<Self, String> {
+        let mut processing = ImageProcessing::default();
+
+        if let Ok(max_dimension) = env_var("IMAGE_MAX_DIMENSION") {
+            processing.max_dimension = max_dimension
+                .parse()
+                .map_err(|_| format!("IMAGE_MAX_DIMENSION is not a number: {max_dimension}"))?;
+        }
+        if let Ok(quality) = env_var("IMAGE_QUALITY") {
+            processing.quality = match quality.parse() {
+                Ok(quality @ 1..=100) => quality,
+                _ => return Err(format!("IMAGE_QUALITY has to be 1 to 100: {quality}")),
+            };
+        }
+        if let Ok(convert_to_jpeg) = env_var("IMAGE_CONVERT_TO_JPEG") {
+            processing.convert_to_jpeg = convert_to_jpeg != "false";
+        }
+
+        Ok(processing)
+    }
+
+    pub fn process(&self, image: Image) -> Result<Image> {
+        // every image is re-encoded, webp and heic can carry EXIF data as well
+        let (decoded, format) = match image.content_type.as_str() {
+            "image/jpeg" => (decode(&image.content)?, ImageFormat::Jpeg),
+            // screenshots are usually png and would look worse as jpeg
+            "image/png" => (decode(&image.content)?, ImageFormat::Png),
+            "image/webp" if self.convert_to_jpeg => (decode(&image.content)?, ImageFormat::Jpeg),
+            "image/webp" => (decode(&image.content)?, ImageFormat::WebP),
+            // there is no HEIC encoder to keep the format
+            #[cfg(feature = "heic")]
+            "image/heic" => (decode_heic(&image.content)?, ImageFormat::Jpeg),
+            _ => return Ok(image),
+        };
+
+        let decoded =
+            if decoded.width() > self.max_dimension || decoded.height() > self.max_dimension {
+                // keeps the aspect ratio
+                decoded.resize(self.max_dimension, self.max_dimension, FilterType::Lanczos3)
+            } else {
+                decoded
+            };
+
+        let stem = image
+            .filename
+            .rsplit_once('.')
+            .map(|(stem, _)| stem)
+            .unwrap_or(&image.filename);
+        let mut content = vec![];
+
+        let (extension, content_type) = match format {
+            ImageFormat::Png => {
+                decoded
+                    .write_to(&mut Cursor::new(&mut content), ImageFormat::Png)
+                    .map_err(processing_error)?;
+                ("png", mime::IMAGE_PNG.to_string())
+            }
+            ImageFormat::WebP => {
+                // the encoder only supports lossless webp
+                DynamicImage::ImageRgba8(decoded.to_rgba8())
+                    .write_with_encoder(WebPEncoder::new_lossless(&mut content))
+                    .map_err(processing_error)?;
+                ("webp", "image/webp".to_string())
+            }
+            _ => {
+                // jpeg has no alpha channel
+                DynamicImage::ImageRgb8(decoded.to_rgb8())
+                    .write_with_encoder(JpegEncoder::new_with_quality(&mut content, self.quality))
+                    .map_err(processing_error)?;
+                ("jpg", mime::IMAGE_JPEG.to_string())
+            }
+        };
+
+        Ok(Image {
+            filename: format!("{stem}.{extension}"),
+            content_type,
+            content,
+        })
+    }
+}
+
+fn processing_error(err: ImageError) -> BotError {
+    BotError::ImageProcessing(err.to_string())
+}
+
+/// Decodes the image upright, phone photos are often rotated by their EXIF orientation,
+/// which is dropped with the rest of the metadata.
+fn decode(content: &[u8]) -> Result<DynamicImage> {
+    let mut decoder = ImageReader::new(Cursor::new(content))
+        .with_guessed_format()
+        .map_err(|err| BotError::ImageProcessing(err.to_string()))?
+        .into_decoder()
+        .map_err(processing_error)?;
+    let orientation = decoder.orientation().map_err(processing_error)?;
+
+    let mut decoded = DynamicImage::from_decoder(decoder).map_err(processing_error)?;
+    decoded.apply_orientation(orientation);
+
+    Ok(decoded)
+}
+
+#[cfg(feature = "heic")]
+fn decode_heic(content: &[u8]) -> Result<DynamicImage> {
+    use libheif_rs::{ColorSpace, HeifContext, LibHeif, RgbChroma};
+
+    let heic_error = |err: libheif_rs::HeifError| BotError::ImageProcessing(err.to_string());
+
+    let context = HeifContext::read_from_bytes(content).map_err(heic_error)?;
+    let handle = context.primary_image_handle().map_err(heic_error)?;
+    let decoded = LibHeif::new()
+        .decode(&handle, ColorSpace::Rgb(RgbChroma::Rgb), None)
+        .map_err(heic_error)?;
+
+    let planes = decoded.planes();
+    let Some(plane) = planes.interleaved else {
+        return Err(BotError::ImageProcessing(
+            "HEIC image has no RGB data".to_string(),
+        ));
+    };
+
+    // rows can be padded, the padding is dropped
+    let row_length = plane.width as usize * 3;
+    let pixels = plane
+        .data
+        .chunks(plane.stride)
+        .take(plane.height as usize)
+        .flat_map(|row| &row[..row_length])
+        .copied()
+        .collect();
+
+    image::RgbImage::from_raw(plane.width, plane.height, pixels)
+        .map(DynamicImage::ImageRgb8)
+        .ok_or_else(|| BotError::ImageProcessing("invalid HEIC image size".to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn unsupported_images_are_unchanged() {
+        let image = Image {
+            filename: "file_1.gif".to_string(),
+            content_type: "image/gif".to_string(),
+            content: vec![1, 2, 3],
+        };
+
+        let processed = ImageProcessing::default().process(image).unwrap();
+
+        assert_eq!("file_1.gif", processed.filename);
+        assert_eq!(vec![1, 2, 3], processed.content);
+    }
+
+    /// EXIF data in TIFF layout with only an orientation tag.
+    fn exif_with_orientation(orientation: u8) -> Vec<u8> {
+        let mut exif = b"II*\0\x08\0\0\0".to_vec();
+        exif.extend_from_slice(&[1, 0]);
+        exif.extend_from_slice(&[0x12, 0x01, 3, 0, 1, 0, 0, 0, orientation, 0, 0, 0]);
+        exif.extend_from_slice(&[0, 0, 0, 0]);
+        exif
+    }
+
+    fn jpeg(width: u32, height: u32, exif: Vec<u8>) -> Image {
+        use image::ImageEncoder;
+
+        let pixels = image::RgbImage::from_fn(width, height, |x, y| {
+            image::Rgb([(x % 256) as u8, (y % 256) as u8, ((x * y) % 256) as u8])
+        });
+        let mut content = vec![];
+        let mut encoder = JpegEncoder::new_with_quality(&mut content, 95);
+        encoder.set_exif_metadata(exif).unwrap();
+        encoder
+            .write_image(&pixels, width, height, image::ExtendedColorType::Rgb8)
+            .unwrap();
+
+        Image {
+            filename: "file_1.jpg".to_string(),
+            content_type: "image/jpeg".to_string(),
+            content,
+        }
+    }
+
+    #[test]
+    fn metadata_is_dropped_and_orientation_applied() {
+        let image = jpeg(40, 20, exif_with_orientation(6));
+        assert!(image.content.windows(4).any(|bytes| bytes == b"Exif"));
+
+        let processed = ImageProcessing::default().process(image).unwrap();
+
+        assert!(!processed.content.windows(4).any(|bytes| bytes == b"Exif"));
+        // rotated by 90 degrees, as the orientation asked for
+        let decoded = image::load_from_memory(&processed.content).unwrap();
+        assert_eq!((20, 40), (decoded.width(), decoded.height()));
+    }
+
+    #[test]
+    fn large_images_are_resized() {
+        let processing = ImageProcessing {
+            max_dimension: 100,
+            ..ImageProcessing::default()
+        };
+
+        let processed = processing.process(jpeg(400, 200, vec![])).unwrap();
+
+        let decoded = image::load_from_memory(&processed.content).unwrap();
+        assert_eq!((100, 50), (decoded.width(), decoded.height()));
+    }
+
+    #[test]
+    fn quality_is_applied() {
+        let process = |quality| {
+            let processing = ImageProcessing {
+                quality,
+                ..ImageProcessing::default()
+            };
+            processing
+                .process(jpeg(200, 200, vec![]))
+                .unwrap()
+                .content
+                .len()
+        };
+
+        assert!(process(10) < process(95));
+    }
+
+    #[test]
+    fn webp_stays_webp_without_conversion() {
+        let mut content = vec![];
+        DynamicImage::new_rgba8(10, 10)
+            .write_with_encoder(WebPEncoder::new_lossless(&mut content))
+            .unwrap();
+        let image = Image {
+            filename: "file_1.webp".to_string(),
+            content_type: "image/webp".to_string(),
+            content,
+        };
+        let processing = ImageProcessing {
+            convert_to_jpeg: false,
+            ..ImageProcessing::default()
+        };
+
+        let processed = processing.process(image).unwrap();
+
+        assert_eq!("file_1.webp", processed.filename);
+        assert_eq!("image/webp", processed.content_type);
+    }
+}
@@ -0,0 +1,115 @@
+use async_trait::async_trait;
+use s3::{creds::Credentials, Bucket, Region};
+
+use super::{content_name, env_var, Image, ImageStorage};
+use crate::error::{BotError, Result};
+
+/// Stores images in a bucket of any S3 compatible object storage, e.g. MinIO.
+pub struct S3Storage {
+    bucket: Bucket,
+    /// url the bucket is publicly readable at
+    public_url: String,
+}
+
+impl S3Storage {
+    /// Reads `S3_BUCKET`, `S3_ENDPOINT`, `S3_ACCESS_KEY` and `S3_SECRET_KEY`,
+    /// optionally `S3_REGION` and `S3_PUBLIC_URL` if the bucket is served elsewhere.
+    pub fn from_env() -> std::result::Result<Self, String> {
+        let name = env_var("S3_BUCKET")?;
+        let endpoint = env_var("S3_ENDPOINT")?;
+        let region = std::env::var("S3_REGION").unwrap_or("us-east-1".to_string());
+        let public_url = std::env::var("S3_PUBLIC_URL")
+            .unwrap_or(format!("{}/{name}", endpoint.trim_end_matches('/')));
+
+        let credentials = Credentials::new(
+            Some(&env_var("S3_ACCESS_KEY")?),
+            Some(&env_var("S3_SECRET_KEY")?),
+            None,
+            None,
+            None,
+        )
+        .map_err(|err| err.to_string())?;
+
+        Self::new(
+            &name,
+            Region::Custom { region, endpoint },
+            credentials,
+            public_url,
+        )
+    }
+
+    pub fn new(
+        name: &str,
+        region: Region,
+        credentials: Credentials,
+        public_url: String,
+    ) -> std::result::Result<Self, String> {
+        // path style urls work with every S3 compatible storage
+        let bucket = Bucket::new(name, region, credentials)
+            .map_err(|err| err.to_string())?
+            .with_path_style();
+
+        Ok(S3Storage {
+            bucket,
+            public_url: public_url.trim_end_matches('/').to_string(),
+        })
+    }
+}
+
+#[async_trait]
+impl ImageStorage for S3Storage {
+    async fn upload(&self, image: &Image) -> Result<String> {
+        let name = content_name(image);
+
+        let resp = self
+            .bucket
+            .put_object_with_content_type(&name, &image.content, &image.content_type)
+            .await
+            .map_err(|err| BotError::ImageHostDown(err.to_string()))?;
+
+        if resp.status_code() != 200 {
+            return Err(BotError::ImageHostDown(format!(
+                "storing the image failed with status {}",
+                resp.status_code()
+            )));
+        }
+
+        Ok(format!("{}/{name}", self.public_url))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // needs a running MinIO with an "images" bucket:
+    // docker run -p 9000:9000 minio/minio server /data
+    #[tokio::test]
+    #[ignore]
+    async fn stores_image_in_minio() {
+        let credentials =
+            Credentials::new(Some("minioadmin"), Some("minioadmin"), None, None, None).unwrap();
+        let region = Region::Custom {
+            region: "us-east-1".to_string(),
+            endpoint: "http://localhost:9000".to_string(),
+        };
+        let storage = S3Storage::new(
+            "images",
+            region,
+            credentials,
+            "http://localhost:9000/images".to_string(),
+        )
+        .unwrap();
+        let image = Image {
+            filename: "file_1.png".to_string(),
+            content_type: "image/png".to_string(),
+            content: vec![4, 5, 6],
+        };
+
+        let url = storage.upload(&image).await.unwrap();
+
+        let name = url.strip_prefix("http://localhost:9000/images/").unwrap();
+        let object = storage.bucket.get_object(name).await.unwrap();
+        assert_eq!(vec![4, 5, 6], object.to_vec());
+    }
+}
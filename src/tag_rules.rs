@@ -0,0 +1,221 @@
+use std::{fmt, str::FromStr};
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum RuleKind {
+    /// matches if the link points to the domain or one of its subdomains
+    Domain,
+    /// matches if the text contains the pattern (case insensitive)
+    Keyword,
+}
+
+impl RuleKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            RuleKind::Domain => "domain",
+            RuleKind::Keyword => "keyword",
+        }
+    }
+}
+
+impl fmt::Display for RuleKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl FromStr for RuleKind {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "domain" => Ok(RuleKind::Domain),
+            "keyword" => Ok(RuleKind::Keyword),
+            other => Err(format!(
+                "Unknown rule type \"{other}\", use \"domain\" or \"keyword\""
+            )),
+        }
+    }
+}
+
+#[derive(Clone, PartialEq, Debug)]
+pub struct TagRule {
+    pub id: i64,
+    pub user_id: String,
+    pub kind: RuleKind,
+    pub pattern: String,
+    pub tag: String,
+}
+
+impl TagRule {
+    fn matches(&self, text: &str, url: Option<&str>) -> bool {
+        match self.kind {
+            RuleKind::Domain => url
+                .and_then(get_host)
+                .map(|host| {
+                    let domain = self.pattern.trim_start_matches("www.").to_lowercase();
+                    host == domain || host.ends_with(&format!(".{domain}"))
+                })
+                .unwrap_or(false),
+            RuleKind::Keyword => text.to_lowercase().contains(&self.pattern.to_lowercase()),
+        }
+    }
+}
+
+impl fmt::Display for TagRule {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}: {} \"{}\" → {}",
+            self.id, self.kind, self.pattern, self.tag
+        )
+    }
+}
+
+/// Parses the arguments of `/addrule`: the kind, the pattern and the tag.
+///
+/// Patterns and tags with spaces are given in quotes, tags also as `#[multi word]`.
+pub fn parse_rule(input: &str) -> Result<(RuleKind, String, String), String> {
+    let arguments = split_arguments(input)?;
+    let [kind, pattern, tag] = arguments.as_slice() else {
+        return Err("A rule needs a type, a pattern and a tag".to_string());
+    };
+
+    let tag = tag
+        .strip_prefix("#[")
+        .and_then(|tag| tag.strip_suffix(']'))
+        .or_else(|| tag.strip_prefix('#'))
+        .unwrap_or(tag)
+        .trim();
+    if tag.is_empty() {
+        return Err("The tag of a rule cannot be empty".to_string());
+    }
+
+    Ok((kind.parse()?, pattern.clone(), tag.to_string()))
+}
+
+/// Splits at whitespace, except inside quotes and `#[...]`.
+fn split_arguments(input: &str) -> Result<Vec<String>, String> {
+    let mut arguments = vec![];
+    let mut rest = input.trim_start();
+
+    while !rest.is_empty() {
+        let (argument, remaining) = if let Some(quoted) = rest.strip_prefix('"') {
+            let end = quoted.find('"').ok_or("A quote is not closed")?;
+            (quoted[..end].to_string(), &quoted[end + 1..])
+        } else if rest.starts_with("#[") {
+            let end = rest.find(']').ok_or("A #[ is not closed with ]")?;
+            (rest[..=end].to_string(), &rest[end + 1..])
+        } else {
+            let end = rest.find(char::is_whitespace).unwrap_or(rest.len());
+            (rest[..end].to_string(), &rest[end..])
+        };
+
+        arguments.push(argument);
+        rest = remaining.trim_start();
+    }
+
+    Ok(arguments)
+}
+
+/// Returns the tags of all rules matching the text or url, without duplicates.
+pub fn apply_tag_rules(rules: &[TagRule], text: &str, url: Option<&str>) -> Vec<String> {
+    let mut tags: Vec<String> = vec![];
+
+    for rule in rules.iter().filter(|rule| rule.matches(text, url)) {
+        if !tags.contains(&rule.tag) {
+            tags.push(rule.tag.clone());
+        }
+    }
+
+    tags
+}
+
+fn get_host(url: &str) -> Option<String> {
+    let without_scheme = url.split_once("://").map(|(_, rest)| rest).unwrap_or(url);
+    let authority = without_scheme.split(['/', '?', '#']).next()?;
+    let host = authority.rsplit('@').next()?.split(':').next()?;
+
+    if host.is_empty() {
+        None
+    } else {
+        Some(host.to_lowercase())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rule(kind: RuleKind, pattern: &str, tag: &str) -> TagRule {
+        TagRule {
+            id: 0,
+            user_id: "1".to_string(),
+            kind,
+            pattern: pattern.to_string(),
+            tag: tag.to_string(),
+        }
+    }
+
+    #[test]
+    fn domain_rule_matches_subdomains() {
+        let rules = [rule(RuleKind::Domain, "youtube.com", "video")];
+
+        let tags = apply_tag_rules(&rules, "", Some("https://www.youtube.com/watch?v=1"));
+        assert_eq!(vec!["video".to_string()], tags);
+
+        let tags = apply_tag_rules(&rules, "", Some("https://notyoutube.com/watch?v=1"));
+        assert!(tags.is_empty());
+    }
+
+    #[test]
+    fn keyword_rule_is_case_insensitive() {
+        let rules = [
+            rule(RuleKind::Keyword, "recipe", "cooking"),
+            rule(RuleKind::Domain, "github.com", "code"),
+        ];
+
+        let tags = apply_tag_rules(&rules, "Best Recipe ever", None);
+        assert_eq!(vec!["cooking".to_string()], tags);
+    }
+
+    #[test]
+    fn parses_multi_word_tags() {
+        let expected = (
+            RuleKind::Keyword,
+            "a b".to_string(),
+            "long read".to_string(),
+        );
+        assert_eq!(
+            Ok(expected.clone()),
+            parse_rule(r#"keyword "a b" "long read""#)
+        );
+        assert_eq!(Ok(expected), parse_rule("Keyword \"a b\" #[long read]"));
+
+        assert_eq!(
+            Ok((
+                RuleKind::Domain,
+                "github.com".to_string(),
+                "code".to_string()
+            )),
+            parse_rule("domain github.com #code")
+        );
+        assert!(parse_rule("domain github.com").is_err());
+        assert!(parse_rule("domain github.com long read").is_err());
+        assert!(parse_rule("keyword \"unclosed code").is_err());
+    }
+
+    #[test]
+    fn duplicate_tags_are_merged() {
+        let rules = [
+            rule(RuleKind::Keyword, "rust", "code"),
+            rule(RuleKind::Domain, "github.com", "code"),
+        ];
+
+        let tags = apply_tag_rules(
+            &rules,
+            "rust crate https://github.com/a/b",
+            Some("https://github.com/a/b"),
+        );
+        assert_eq!(vec!["code".to_string()], tags);
+    }
+}
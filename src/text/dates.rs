@@ -0,0 +1,225 @@
+use std::ops::Range;
+
+use chrono::{Datelike, Days, Months, NaiveDate, Weekday};
+use regex::Regex;
+
+const WEEKDAYS: &str = "monday|tuesday|wednesday|thursday|friday|saturday|sunday|mon|tue|tues|wed|thu|thur|thurs|fri|sat|sun";
+const MONTHS: &str = "january|february|march|april|may|june|july|august|september|october|november|december|jan|feb|mar|apr|jun|jul|aug|sep|sept|oct|nov|dec";
+
+/// Finds the first date in a message.
+/// Absolute dates (`2026-11-03`, `03.11.2026`) are recognized anywhere,
+/// natural language dates only after words like "by", "due" or "until",
+/// e.g. "read by next friday" or "due in 2 weeks".
+/// Dates starting in one of the excluded ranges, e.g. in links, are skipped.
+pub fn find_date(text: &str, today: NaiveDate, excluded: &[Range<usize>]) -> Option<NaiveDate> {
+    let is_excluded = |start: usize| excluded.iter().any(|range| range.contains(&start));
+
+    let absolute_reg = Regex::new(r"\b(\d{4}-\d{2}-\d{2}|\d{1,2}\.\d{1,2}\.\d{4})\b").unwrap();
+    if let Some(date) = absolute_reg
+        .find_iter(text)
+        .filter(|m| !is_excluded(m.start()))
+        .find_map(|m| parse_date(m.as_str(), today))
+    {
+        return Some(date);
+    }
+
+    let natural_reg = Regex::new(&format!(
+        r"(?i)\b(?:by|due|on|until|till|before|deadline)\b:?\s+((?:the\s+)?(?:{}))",
+        natural_date_pattern()
+    ))
+    .unwrap();
+
+    for cap in natural_reg.captures_iter(text) {
+        let m = cap.get(1).unwrap();
+        if is_excluded(m.start()) {
+            continue;
+        }
+        if let Some(date) = parse_date(m.as_str(), today) {
+            return Some(date);
+        }
+    }
+
+    None
+}
+
+/// Parses a value that consists only of a date, e.g. `friday` or `2026-11-03`.
+pub fn parse_date(value: &str, today: NaiveDate) -> Option<NaiveDate> {
+    let value = value.trim().to_lowercase();
+    let value = value.strip_prefix("the ").unwrap_or(&value);
+
+    if let Ok(date) = NaiveDate::parse_from_str(value, "%Y-%m-%d") {
+        return Some(date);
+    }
+    if let Ok(date) = NaiveDate::parse_from_str(value, "%d.%m.%Y") {
+        return Some(date);
+    }
+
+    match value {
+        "today" | "tonight" => return Some(today),
+        "tomorrow" => return today.checked_add_days(Days::new(1)),
+        "day after tomorrow" => return today.checked_add_days(Days::new(2)),
+        "yesterday" => return today.checked_sub_days(Days::new(1)),
+        "next week" => return today.checked_add_days(Days::new(7)),
+        "next month" => return today.checked_add_months(Months::new(1)),
+        "next year" => return today.checked_add_months(Months::new(12)),
+        _ => {}
+    }
+
+    let relative_reg =
+        Regex::new(r"^in\s+(\d{1,3}|a|an|one|two|three)\s+(day|week|month|year)s?$").unwrap();
+    if let Some(cap) = relative_reg.captures(value) {
+        let amount: u32 = match &cap[1] {
+            "a" | "an" | "one" => 1,
+            "two" => 2,
+            "three" => 3,
+            number => number.parse().ok()?,
+        };
+
+        return match &cap[2] {
+            "day" => today.checked_add_days(Days::new(amount.into())),
+            "week" => today.checked_add_days(Days::new(u64::from(amount) * 7)),
+            "month" => today.checked_add_months(Months::new(amount)),
+            _ => today.checked_add_months(Months::new(amount * 12)),
+        };
+    }
+
+    let weekday_reg = Regex::new(&format!(r"^(next|this)?\s*({WEEKDAYS})$")).unwrap();
+    if let Some(cap) = weekday_reg.captures(value) {
+        // chrono only knows full names and three letter abbreviations
+        let weekday: Weekday = cap[2][..3].parse().ok()?;
+        let mut days_ahead =
+            (7 + weekday.num_days_from_monday() - today.weekday().num_days_from_monday()) % 7;
+        // "next friday" on a friday means in a week, "friday" means today
+        if days_ahead == 0 && cap.get(1).is_some_and(|m| m.as_str() == "next") {
+            days_ahead = 7;
+        }
+
+        return today.checked_add_days(Days::new(days_ahead.into()));
+    }
+
+    parse_month_date(value, today)
+}
+
+/// `3 november`, `november 3rd`, `nov 3 2026`; without a year the next such date is used.
+fn parse_month_date(value: &str, today: NaiveDate) -> Option<NaiveDate> {
+    let day_first_reg = Regex::new(&format!(
+        r"^(\d{{1,2}})(?:st|nd|rd|th)?\s+({MONTHS})(?:\s+(\d{{4}}))?$"
+    ))
+    .unwrap();
+    let month_first_reg = Regex::new(&format!(
+        r"^({MONTHS})\s+(\d{{1,2}})(?:st|nd|rd|th)?(?:,?\s+(\d{{4}}))?$"
+    ))
+    .unwrap();
+
+    let (day, month, year) = if let Some(cap) = day_first_reg.captures(value) {
+        (cap[1].to_string(), cap[2].to_string(), cap.get(3))
+    } else if let Some(cap) = month_first_reg.captures(value) {
+        (cap[2].to_string(), cap[1].to_string(), cap.get(3))
+    } else {
+        return None;
+    };
+
+    let day: u32 = day.parse().ok()?;
+    let month = month[..3]
+        .parse::<chrono::Month>()
+        .ok()?
+        .number_from_month();
+
+    match year {
+        Some(year) => NaiveDate::from_ymd_opt(year.as_str().parse().ok()?, month, day),
+        None => {
+            let date = NaiveDate::from_ymd_opt(today.year(), month, day)?;
+            if date < today {
+                NaiveDate::from_ymd_opt(today.year() + 1, month, day)
+            } else {
+                Some(date)
+            }
+        }
+    }
+}
+
+fn natural_date_pattern() -> String {
+    [
+        r"day after tomorrow|today|tonight|tomorrow|yesterday".to_string(),
+        r"next (?:week|month|year)".to_string(),
+        r"in\s+(?:\d{1,3}|a|an|one|two|three)\s+(?:day|week|month|year)s?".to_string(),
+        format!(r"(?:(?:next|this)\s+)?(?:{WEEKDAYS})"),
+        format!(r"\d{{1,2}}(?:st|nd|rd|th)?\s+(?:{MONTHS})(?:\s+\d{{4}})?"),
+        format!(r"(?:{MONTHS})\s+\d{{1,2}}(?:st|nd|rd|th)?(?:,?\s+\d{{4}})?"),
+    ]
+    .iter()
+    .map(|pattern| format!(r"(?:{pattern})\b"))
+    .collect::<Vec<String>>()
+    .join("|")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // a monday
+    fn today() -> NaiveDate {
+        NaiveDate::from_ymd_opt(2026, 10, 19).unwrap()
+    }
+
+    fn date(year: i32, month: u32, day: u32) -> Option<NaiveDate> {
+        NaiveDate::from_ymd_opt(year, month, day)
+    }
+
+    #[test]
+    fn absolute_dates() {
+        assert_eq!(date(2026, 11, 3), find_date("due 2026-11-03", today(), &[]));
+        assert_eq!(
+            date(2026, 11, 3),
+            find_date("until 03.11.2026!", today(), &[])
+        );
+    }
+
+    #[test]
+    fn dates_in_links_are_skipped() {
+        let text = "Release notes https://blog.example.com/2024-05-01-release due friday";
+        let links = super::super::link_ranges(text);
+
+        assert_eq!(date(2026, 10, 23), find_date(text, today(), &links));
+        assert_eq!(date(2024, 5, 1), find_date(text, today(), &[]));
+    }
+
+    #[test]
+    fn relative_dates() {
+        assert_eq!(
+            date(2026, 10, 23),
+            find_date("read by next friday", today(), &[])
+        );
+        assert_eq!(
+            date(2026, 10, 23),
+            find_date("read by Friday", today(), &[])
+        );
+        assert_eq!(date(2026, 10, 22), find_date("until thurs", today(), &[]));
+        assert_eq!(
+            date(2026, 10, 26),
+            find_date("due next monday", today(), &[])
+        );
+        assert_eq!(date(2026, 10, 20), find_date("Due: tomorrow", today(), &[]));
+        assert_eq!(date(2026, 11, 2), find_date("due in 2 weeks", today(), &[]));
+        assert_eq!(
+            date(2026, 11, 19),
+            find_date("due in a month", today(), &[])
+        );
+    }
+
+    #[test]
+    fn month_names() {
+        assert_eq!(
+            date(2026, 11, 3),
+            find_date("by November 3rd", today(), &[])
+        );
+        assert_eq!(date(2027, 1, 5), find_date("due 5 jan", today(), &[]));
+        assert_eq!(date(2028, 2, 1), parse_date("feb 1, 2028", today()));
+    }
+
+    #[test]
+    fn no_date_without_keyword() {
+        assert_eq!(None, find_date("Friday night plans", today(), &[]));
+        assert_eq!(None, find_date("on mondays", today(), &[]));
+    }
+}
@@ -0,0 +1,158 @@
+use url::Url;
+
+/// query parameters that only track where a link was shared
+const TRACKING_PARAMETERS: [&str; 16] = [
+    "fbclid", "gclid", "dclid", "gbraid", "wbraid", "msclkid", "yclid", "twclid", "igshid", "igsh",
+    "mc_cid", "mc_eid", "ref_src", "ref_url", "_hsenc", "_hsmi",
+];
+/// `si` only tracks shares on these sites, and their subdomains, elsewhere it may mean anything
+const SHARE_ID_HOSTS: [&str; 3] = ["youtube.com", "youtu.be", "spotify.com"];
+/// hosts of link shorteners, the links are replaced by where they redirect to
+const SHORTENERS: [&str; 11] = [
+    "t.co",
+    "bit.ly",
+    "buff.ly",
+    "ow.ly",
+    "tinyurl.com",
+    "is.gd",
+    "goo.gl",
+    "lnkd.in",
+    "dlvr.it",
+    "trib.al",
+    "amzn.to",
+];
+
+/// Drops punctuation the link pattern swallowed, e.g. in `(see https://example.com).`
+/// Closing brackets are kept if they belong to the link, like on wikipedia.
+pub fn trim_trailing_punctuation(url: &str) -> &str {
+    let mut url = url;
+
+    loop {
+        let Some(last) = url.chars().last() else {
+            return url;
+        };
+        let is_unbalanced = |open: char| url.matches(open).count() < url.matches(last).count();
+
+        let trim = match last {
+            '.' | ',' | ';' | ':' | '!' | '?' | '\'' | '"' | '’' | '”' | '»' => true,
+            ')' => is_unbalanced('('),
+            ']' => is_unbalanced('['),
+            '}' => is_unbalanced('{'),
+            '>' => true,
+            _ => false,
+        };
+        if !trim {
+            return url;
+        }
+
+        url = &url[..url.len() - last.len_utf8()];
+    }
+}
+
+fn is_tracking_parameter(key: &str, host: &str) -> bool {
+    let is_share_host = || {
+        SHARE_ID_HOSTS
+            .iter()
+            .any(|share_host| host == *share_host || host.ends_with(&format!(".{share_host}")))
+    };
+
+    key.starts_with("utm_")
+        || TRACKING_PARAMETERS.contains(&key)
+        || (key == "si" && is_share_host())
+}
+
+/// Removes `utm_*` and other tracking parameters, other urls are returned as they are.
+/// The other parameters are kept exactly as written, some sites tell `+` and `%20` apart.
+pub fn strip_tracking_parameters(url: &str) -> String {
+    let Ok(mut parsed) = Url::parse(url) else {
+        return url.to_string();
+    };
+    let host = parsed.host_str().unwrap_or("").to_lowercase();
+    let Some(query) = parsed.query() else {
+        return url.to_string();
+    };
+
+    let segments: Vec<&str> = query.split('&').collect();
+    let kept: Vec<&str> = segments
+        .iter()
+        .copied()
+        .filter(|segment| {
+            let key = segment.split('=').next().unwrap_or(segment);
+            !is_tracking_parameter(key, &host)
+        })
+        .collect();
+    if kept.len() == segments.len() {
+        return url.to_string();
+    }
+
+    let kept = kept.join("&");
+    parsed.set_query(Some(kept.as_str()).filter(|query| !query.is_empty()));
+
+    parsed.to_string()
+}
+
+pub fn is_shortened(url: &str) -> bool {
+    Url::parse(url)
+        .ok()
+        .and_then(|url| {
+            url.host_str()
+                .map(|host| host.trim_start_matches("www.").to_string())
+        })
+        .is_some_and(|host| SHORTENERS.contains(&host.as_str()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn trims_punctuation() {
+        assert_eq!(
+            "https://example.com/page",
+            trim_trailing_punctuation("https://example.com/page).")
+        );
+        assert_eq!(
+            "https://en.wikipedia.org/wiki/Rust_(programming_language)",
+            trim_trailing_punctuation("https://en.wikipedia.org/wiki/Rust_(programming_language),")
+        );
+    }
+
+    #[test]
+    fn strips_tracking_parameters() {
+        assert_eq!(
+            "https://example.com/article?id=3",
+            strip_tracking_parameters(
+                "https://example.com/article?utm_source=news&id=3&fbclid=abc"
+            )
+        );
+        assert_eq!(
+            "https://youtu.be/dQw4w9WgXcQ",
+            strip_tracking_parameters("https://youtu.be/dQw4w9WgXcQ?si=abc")
+        );
+        assert_eq!(
+            "https://open.spotify.com/track/4uLU6hMCjMI75M1A2tKUQC",
+            strip_tracking_parameters(
+                "https://open.spotify.com/track/4uLU6hMCjMI75M1A2tKUQC?si=abc"
+            )
+        );
+        assert!(is_shortened("https://t.co/abc"));
+    }
+
+    #[test]
+    fn other_parameters_are_kept_as_written() {
+        assert_eq!(
+            "https://example.com/search?q=rust%20lang&flag",
+            strip_tracking_parameters(
+                "https://example.com/search?q=rust%20lang&utm_medium=social&flag"
+            )
+        );
+        assert_eq!(
+            "https://example.com/page?si=2",
+            strip_tracking_parameters("https://example.com/page?si=2")
+        );
+        assert_eq!(
+            "https://example.com/page?a=1&b=c+d",
+            strip_tracking_parameters("https://example.com/page?a=1&b=c+d")
+        );
+    }
+}
@@ -0,0 +1,167 @@
+mod dates;
+mod links;
+mod properties;
+mod tags;
+
+use regex::Regex;
+use std::ops::Range;
+
+pub use dates::{find_date, parse_date};
+use links::trim_trailing_punctuation;
+pub use links::{is_shortened, strip_tracking_parameters};
+use properties::{find_properties, property_line};
+pub use tags::TagOptions;
+use tags::{find_tags, strip_tags};
+
+#[derive(Default)]
+pub struct TextElements {
+    pub title: Option<String>,
+    pub url: Option<String>,
+    pub tags: Option<Vec<String>>,
+    /// `key: value` lines whose key is a property, their values are not yet checked
+    pub properties: Vec<(String, String)>,
+}
+
+impl TextElements {
+    pub fn add_tags(&mut self, new_tags: Vec<String>) {
+        if new_tags.is_empty() {
+            return;
+        }
+
+        let tags = self.tags.get_or_insert_with(Vec::new);
+        for tag in new_tags {
+            if !tags.contains(&tag) {
+                tags.push(tag);
+            }
+        }
+    }
+
+    /// Whether the message has no title besides the link itself.
+    pub fn title_is_missing(&self) -> bool {
+        let title = self.title.as_deref().unwrap_or("").trim();
+
+        title.is_empty() || Some(title) == self.url.as_deref()
+    }
+}
+
+/// The byte ranges of the links in the text, without trailing punctuation.
+pub fn link_ranges(text: &str) -> Vec<Range<usize>> {
+    let links_reg: Regex = Regex::new(r"(https?:\/\/[^\s]+)").unwrap();
+
+    links_reg
+        .find_iter(text)
+        .map(|m| m.start()..m.start() + trim_trailing_punctuation(m.as_str()).len())
+        .collect()
+}
+
+/// Splits the message into its title, link, tags and property lines.
+/// `is_property` tells whether a key names a property of the database,
+/// other `key: value` lines like "Note: buy milk" are ordinary text.
+pub fn handle_text(
+    text: String,
+    tag_options: &TagOptions,
+    is_property: impl Fn(&str) -> bool,
+) -> TextElements {
+    let links = link_ranges(&text);
+    let first_link = links.first().map(|range| &text[range.clone()]);
+    let url = first_link.map(strip_tracking_parameters);
+
+    let tags = find_tags(&text, tag_options, &links);
+
+    let properties: Vec<(String, String)> = find_properties(&text)
+        .into_iter()
+        .filter(|(key, _)| is_property(key))
+        .collect();
+
+    // property lines are not part of the title
+    let (first_line_start, first_line) = text
+        .split_inclusive('\n')
+        .scan(0, |start, line| {
+            let line_start = *start;
+            *start += line.len();
+            Some((line_start, line.trim_end()))
+        })
+        .find(|(_, line)| !property_line(line).is_some_and(|(key, _)| is_property(&key)))
+        .unwrap_or((0, ""));
+    let mut title = strip_tags(first_line, first_line_start, &tags);
+    // a message with only the link gets the cleaned link as title, also when followed by punctuation
+    if Some(trim_trailing_punctuation(title.trim())) == first_link {
+        title = url.clone().unwrap_or(title);
+    }
+
+    let mut text_elements = TextElements {
+        title: Some(title),
+        url,
+        tags: None,
+        properties,
+    };
+    text_elements.add_tags(tags.into_iter().map(|tag| tag.name).collect());
+
+    text_elements
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn title_without_tags() {
+        let text = "#read Interesting article #[long read]\nhttps://example.com/#top #web";
+
+        let text_elements = handle_text(text.to_string(), &TagOptions::default(), |_| false);
+
+        assert_eq!(Some("Interesting article".to_string()), text_elements.title);
+        assert_eq!(
+            Some("https://example.com/#top".to_string()),
+            text_elements.url
+        );
+        assert_eq!(
+            Some(vec![
+                "read".to_string(),
+                "long read".to_string(),
+                "web".to_string()
+            ]),
+            text_elements.tags
+        );
+    }
+
+    #[test]
+    fn property_lines_are_not_the_title() {
+        let text = "rating:4\nstatus: to-read\nGreat #book";
+
+        let text_elements = handle_text(text.to_string(), &TagOptions::default(), |_| true);
+
+        assert_eq!(Some("Great".to_string()), text_elements.title);
+        assert_eq!(Some(vec!["book".to_string()]), text_elements.tags);
+        assert_eq!(2, text_elements.properties.len());
+    }
+
+    #[test]
+    fn lines_of_other_keys_are_the_title() {
+        let text = "Breaking: storm hits coast\nstatus: to-read";
+
+        let text_elements = handle_text(text.to_string(), &TagOptions::default(), |key| {
+            key == "status"
+        });
+
+        assert_eq!(
+            Some("Breaking: storm hits coast".to_string()),
+            text_elements.title
+        );
+        assert_eq!(
+            vec![("status".to_string(), "to-read".to_string())],
+            text_elements.properties
+        );
+    }
+
+    #[test]
+    fn link_with_punctuation_is_the_cleaned_link() {
+        let text = "https://example.com/a?utm_source=x).";
+
+        let text_elements = handle_text(text.to_string(), &TagOptions::default(), |_| false);
+
+        assert_eq!(Some("https://example.com/a".to_string()), text_elements.url);
+        assert_eq!(text_elements.url, text_elements.title);
+        assert!(text_elements.title_is_missing());
+    }
+}
@@ -0,0 +1,51 @@
+use regex::Regex;
+
+/// Finds lines of the form `key: value`.
+/// Which of them are actual properties is decided later against the database schema.
+pub fn find_properties(text: &str) -> Vec<(String, String)> {
+    text.lines().filter_map(property_line).collect()
+}
+
+/// The key and value if the line is of the form `key: value` or `key:value`.
+pub fn property_line(line: &str) -> Option<(String, String)> {
+    let property_reg = Regex::new(r"^\s*(\p{L}[\w \-]{0,49}?)\s*:(\s*)(\S.*?)\s*$").unwrap();
+    let cap = property_reg.captures(line)?;
+    let key = cap.get(1).unwrap().as_str();
+    let value = cap.get(3).unwrap().as_str();
+
+    // without a space after the colon only single words are keys,
+    // so links like https://... and times like "at 10:30" are not matched
+    if cap.get(2).unwrap().as_str().is_empty()
+        && (key.contains(char::is_whitespace) || value.starts_with("//"))
+    {
+        return None;
+    }
+
+    Some((key.to_string(), value.to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn key_value_lines() {
+        let text =
+            "Some article https://example.com\nstatus: to-read\n  Due date : 2026-11-03\nrating:4";
+
+        assert_eq!(
+            vec![
+                ("status".to_string(), "to-read".to_string()),
+                ("Due date".to_string(), "2026-11-03".to_string()),
+                ("rating".to_string(), "4".to_string()),
+            ],
+            find_properties(text)
+        );
+    }
+
+    #[test]
+    fn links_and_times_are_no_properties() {
+        assert_eq!(None, property_line("https://example.com"));
+        assert_eq!(None, property_line("Meeting at 10:30"));
+    }
+}
@@ -0,0 +1,149 @@
+use std::ops::Range;
+
+use regex::Regex;
+
+#[derive(Clone, Default)]
+pub struct TagOptions {
+    /// also treat `@mention` like `#tag`
+    pub mentions_as_tags: bool,
+}
+
+pub struct TagToken {
+    /// byte range of the whole token (including `#` and brackets) in the text
+    pub range: Range<usize>,
+    pub name: String,
+}
+
+/// Finds `#tag`, `#[multi word tag]` and optionally `@tag` tokens.
+/// A tag has to start the text or follow a character that is not part of a word,
+/// so `C#` or anchors in links are not picked up.
+pub fn find_tags(text: &str, options: &TagOptions, excluded: &[Range<usize>]) -> Vec<TagToken> {
+    let tags_reg = Regex::new(r"(?:^|[^\w&/])([#@])(?:\[([^\]\n]+)\]|(\w[\w\-]*))").unwrap();
+
+    tags_reg
+        .captures_iter(text)
+        .filter_map(|cap| {
+            let sigil = cap.get(1).unwrap();
+            if sigil.as_str() == "@" && !options.mentions_as_tags {
+                return None;
+            }
+
+            let (name, end) = match (cap.get(2), cap.get(3)) {
+                // skip the closing bracket
+                (Some(multi_word), _) => (normalize_tag(multi_word.as_str()), multi_word.end() + 1),
+                (None, Some(word)) => (word.as_str().trim_end_matches('-').to_string(), word.end()),
+                (None, None) => return None,
+            };
+
+            let range = sigil.start()..end;
+            let is_excluded = excluded
+                .iter()
+                .any(|excluded| excluded.contains(&range.start));
+
+            if name.is_empty() || is_excluded {
+                None
+            } else {
+                Some(TagToken { range, name })
+            }
+        })
+        .collect()
+}
+
+/// Removes the tag tokens from a line of text and collapses the remaining whitespace.
+pub fn strip_tags(line: &str, line_offset: usize, tags: &[TagToken]) -> String {
+    let line_range = line_offset..line_offset + line.len();
+
+    let mut stripped = String::with_capacity(line.len());
+    let mut position = line_offset;
+    for tag in tags
+        .iter()
+        .filter(|tag| line_range.contains(&tag.range.start))
+    {
+        stripped.push_str(&line[position - line_offset..tag.range.start - line_offset]);
+        position = tag.range.end.min(line_range.end);
+    }
+    stripped.push_str(&line[position - line_offset..]);
+
+    stripped.split_whitespace().collect::<Vec<&str>>().join(" ")
+}
+
+fn normalize_tag(tag: &str) -> String {
+    // notion does not allow commas in select options
+    tag.replace(',', " ")
+        .split_whitespace()
+        .collect::<Vec<&str>>()
+        .join(" ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tag_names(text: &str, options: &TagOptions) -> Vec<String> {
+        find_tags(text, options, &[])
+            .into_iter()
+            .map(|tag| tag.name)
+            .collect()
+    }
+
+    #[test]
+    fn tag_at_start_of_text() {
+        assert_eq!(
+            vec!["rust", "code"],
+            tag_names("#rust is great #code", &TagOptions::default())
+        );
+    }
+
+    #[test]
+    fn multi_word_tags() {
+        assert_eq!(
+            vec!["to read", "later"],
+            tag_names("article #[to  read] (#later)", &TagOptions::default())
+        );
+    }
+
+    #[test]
+    fn unicode_tags() {
+        assert_eq!(
+            vec!["café", "日本語", "straße"],
+            tag_names("#café #日本語 #straße", &TagOptions::default())
+        );
+    }
+
+    #[test]
+    fn mentions_are_optional() {
+        let text = "thanks @someone #tag";
+
+        assert_eq!(vec!["tag"], tag_names(text, &TagOptions::default()));
+        assert_eq!(
+            vec!["someone", "tag"],
+            tag_names(
+                text,
+                &TagOptions {
+                    mentions_as_tags: true
+                }
+            )
+        );
+    }
+
+    #[test]
+    fn no_tags_inside_words_or_links() {
+        let text = "C# and https://example.com/#anchor";
+        let link = text.find("https").unwrap()..text.len();
+
+        assert!(find_tags(text, &TagOptions::default(), std::slice::from_ref(&link)).is_empty());
+    }
+
+    #[test]
+    fn strip_tags_from_line() {
+        let text = "#rust Some #[long tag] title #end\nsecond #line";
+        let tags = find_tags(text, &TagOptions::default(), &[]);
+
+        let first_line = text.lines().next().unwrap();
+        assert_eq!("Some title", strip_tags(first_line, 0, &tags));
+
+        let second_line = text.lines().nth(1).unwrap();
+        let offset = text.find("second").unwrap();
+        assert_eq!("second", strip_tags(second_line, offset, &tags));
+    }
+}
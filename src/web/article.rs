@@ -0,0 +1,442 @@
+use std::collections::HashMap;
+
+use scraper::{node::Element, ElementRef, Html, Node, Selector};
+use url::Url;
+
+use crate::notion::{ContentBlock, PageFile};
+
+/// Never part of the article.
+const SKIPPED_ELEMENTS: [&str; 14] = [
+    "script",
+    "style",
+    "noscript",
+    "nav",
+    "header",
+    "footer",
+    "aside",
+    "form",
+    "iframe",
+    "svg",
+    "button",
+    "template",
+    "figcaption",
+    "table",
+];
+const INLINE_ELEMENTS: [&str; 17] = [
+    "a", "abbr", "b", "bdi", "cite", "code", "em", "i", "kbd", "mark", "q", "s", "small", "span",
+    "strong", "sub", "sup",
+];
+/// starts of class names and ids of navigation, comments and the like
+const UNLIKELY_CANDIDATES: [&str; 14] = [
+    "comment",
+    "footer",
+    "sidebar",
+    "nav",
+    "menu",
+    "share",
+    "social",
+    "promo",
+    "related",
+    "advert",
+    "banner",
+    "cookie",
+    "newsletter",
+    "subscribe",
+];
+/// shorter paragraphs are usually captions, bylines or buttons
+const MIN_PARAGRAPH_LENGTH: usize = 25;
+const MAX_ARTICLE_BLOCKS: usize = 500;
+
+/// The main content of a web page.
+#[derive(Debug, PartialEq)]
+pub struct Article {
+    pub title: Option<String>,
+    pub blocks: Vec<ContentBlock>,
+}
+
+/// Finds the element containing most of the page's text, similar to readability,
+/// and converts its content to blocks. Relative links are resolved against `url`.
+pub fn extract_article(html: &str, url: &str) -> Option<Article> {
+    let document = Html::parse_document(html);
+    let base_url = Url::parse(url).ok()?;
+
+    let paragraphs = Selector::parse("p, pre").unwrap();
+    let mut scores: HashMap<_, f64> = HashMap::new();
+    for paragraph in document.select(&paragraphs) {
+        if is_unlikely(paragraph) {
+            continue;
+        }
+
+        let text = collapse_whitespace(paragraph.text());
+        let length = text.chars().count();
+        if length < MIN_PARAGRAPH_LENGTH {
+            continue;
+        }
+
+        // commas are a good hint for prose
+        let score = 1.0 + (length.min(300) as f64 / 100.0) + text.matches(',').count() as f64;
+        if let Some(parent) = paragraph.parent() {
+            *scores.entry(parent.id()).or_default() += score;
+            if let Some(grandparent) = parent.parent() {
+                *scores.entry(grandparent.id()).or_default() += score / 2.0;
+            }
+        }
+    }
+
+    let (content, _) = scores
+        .into_iter()
+        .filter_map(|(id, score)| {
+            let element = ElementRef::wrap(document.tree.get(id)?)?;
+            let bonus = match element.value().name() {
+                "article" | "main" => 1.5,
+                _ => 1.0,
+            };
+            Some((element, score * bonus * (1.0 - link_density(element))))
+        })
+        .max_by(|(_, a), (_, b)| a.total_cmp(b))?;
+
+    let mut blocks = vec![];
+    convert_children(content, &base_url, &mut blocks);
+    blocks.truncate(MAX_ARTICLE_BLOCKS);
+
+    if blocks.is_empty() {
+        return None;
+    }
+
+    Some(Article {
+        title: find_title(&document),
+        blocks,
+    })
+}
+
+fn find_title(document: &Html) -> Option<String> {
+    let meta = Selector::parse("meta").unwrap();
+    let og_title = document
+        .select(&meta)
+        .find(|element| element.value().attr("property") == Some("og:title"))
+        .and_then(|element| element.value().attr("content"))
+        .map(|title| title.trim().to_string());
+
+    let title = Selector::parse("title").unwrap();
+    og_title
+        .or_else(|| {
+            document
+                .select(&title)
+                .next()
+                .map(|element| collapse_whitespace(element.text()))
+        })
+        .filter(|title| !title.is_empty())
+}
+
+fn is_unlikely(element: ElementRef) -> bool {
+    // the names of the article and the layout around it say nothing about a paragraph
+    let mut check_names = true;
+
+    for node in std::iter::once(*element).chain(element.ancestors()) {
+        let Some(element) = ElementRef::wrap(node) else {
+            continue;
+        };
+        let element = element.value();
+        if SKIPPED_ELEMENTS.contains(&element.name()) {
+            return true;
+        }
+
+        if matches!(element.name(), "article" | "main" | "body" | "html") {
+            check_names = false;
+        }
+        if check_names && has_unlikely_name(element) {
+            return true;
+        }
+    }
+
+    false
+}
+
+/// Whether a class name or the id starts like one of `UNLIKELY_CANDIDATES`,
+/// e.g. `comment-list` does, while `layout-with-sidebar` does not.
+fn has_unlikely_name(element: &Element) -> bool {
+    element
+        .attr("class")
+        .unwrap_or("")
+        .split_whitespace()
+        .chain(element.id())
+        .map(str::to_lowercase)
+        .any(|name| {
+            UNLIKELY_CANDIDATES
+                .iter()
+                .any(|candidate| name.starts_with(candidate))
+        })
+}
+
+/// Share of the text that is part of links, high for navigation and link lists.
+fn link_density(element: ElementRef) -> f64 {
+    let length = collapse_whitespace(element.text()).chars().count();
+    if length == 0 {
+        return 1.0;
+    }
+
+    let links = Selector::parse("a").unwrap();
+    let link_length: usize = element
+        .select(&links)
+        .map(|link| collapse_whitespace(link.text()).chars().count())
+        .sum();
+
+    link_length as f64 / length as f64
+}
+
+fn collapse_whitespace<'a>(text: impl Iterator<Item = &'a str>) -> String {
+    text.collect::<String>()
+        .split_whitespace()
+        .collect::<Vec<&str>>()
+        .join(" ")
+}
+
+/// Text of an element without the text of nested lists.
+fn own_text(element: ElementRef) -> String {
+    let mut text = String::new();
+    for child in element.descendants() {
+        let nested_list = child
+            .ancestors()
+            .take_while(|node| node.id() != element.id())
+            .any(|node| {
+                ElementRef::wrap(node)
+                    .is_some_and(|node| matches!(node.value().name(), "ul" | "ol"))
+            });
+        if let (Some(content), false) = (child.value().as_text(), nested_list) {
+            text.push_str(content);
+        }
+    }
+
+    collapse_whitespace(std::iter::once(text.as_str()))
+}
+
+fn convert_children(element: ElementRef, base_url: &Url, blocks: &mut Vec<ContentBlock>) {
+    // text and inline elements between blocks, e.g. in a div without paragraphs
+    let mut inline_text = String::new();
+
+    for child in element.children() {
+        match child.value() {
+            Node::Text(text) => inline_text.push_str(text),
+            Node::Element(_) => {
+                let child = ElementRef::wrap(child).unwrap();
+                let name = child.value().name();
+
+                if INLINE_ELEMENTS.contains(&name) {
+                    inline_text.extend(child.text());
+                    continue;
+                }
+                if name == "br" {
+                    inline_text.push('\n');
+                    continue;
+                }
+
+                flush_paragraph(&mut inline_text, blocks);
+                convert_element(child, base_url, blocks);
+            }
+            _ => {}
+        }
+    }
+
+    flush_paragraph(&mut inline_text, blocks);
+}
+
+fn flush_paragraph(inline_text: &mut String, blocks: &mut Vec<ContentBlock>) {
+    let text = collapse_whitespace(std::iter::once(inline_text.as_str()));
+    if !text.is_empty() {
+        blocks.push(ContentBlock::Paragraph(text));
+    }
+    inline_text.clear();
+}
+
+fn convert_element(element: ElementRef, base_url: &Url, blocks: &mut Vec<ContentBlock>) {
+    let name = element.value().name();
+    if SKIPPED_ELEMENTS.contains(&name) || is_unlikely(element) {
+        return;
+    }
+
+    match name {
+        "h1" | "h2" | "h3" | "h4" | "h5" | "h6" => {
+            let text = collapse_whitespace(element.text());
+            if !text.is_empty() {
+                blocks.push(ContentBlock::Heading {
+                    level: name[1..].parse::<u8>().unwrap_or(3).min(3),
+                    text,
+                });
+            }
+        }
+        "p" => {
+            convert_children(element, base_url, blocks);
+        }
+        "ul" | "ol" => {
+            for item in element.children().filter_map(ElementRef::wrap) {
+                if item.value().name() != "li" {
+                    continue;
+                }
+
+                let text = own_text(item);
+                if !text.is_empty() {
+                    blocks.push(match name {
+                        "ol" => ContentBlock::NumberedListItem(text),
+                        _ => ContentBlock::BulletedListItem(text),
+                    });
+                }
+                // nested lists are flattened
+                let lists = Selector::parse("ul, ol").unwrap();
+                for list in item.select(&lists) {
+                    if list.parent().map(|parent| parent.id()) == Some(item.id()) {
+                        convert_element(list, base_url, blocks);
+                    }
+                }
+            }
+        }
+        "blockquote" => {
+            let text = collapse_whitespace(element.text());
+            if !text.is_empty() {
+                blocks.push(ContentBlock::Quote(text));
+            }
+        }
+        "pre" => {
+            let text: String = element.text().collect();
+            if !text.trim().is_empty() {
+                blocks.push(ContentBlock::Code {
+                    language: code_language(element),
+                    text: text.trim_end().to_string(),
+                });
+            }
+        }
+        "img" => {
+            if let Some(url) = image_url(element, base_url) {
+                blocks.push(ContentBlock::Image(PageFile::External(url)));
+            }
+        }
+        "hr" => {}
+        _ => convert_children(element, base_url, blocks),
+    }
+}
+
+fn image_url(element: ElementRef, base_url: &Url) -> Option<String> {
+    let image = element.value();
+    // tracking pixels
+    if image.attr("width") == Some("1") || image.attr("height") == Some("1") {
+        return None;
+    }
+
+    // lazy loaded images often only have a placeholder as src
+    let src = image.attr("data-src").or(image.attr("src"))?;
+    let url = base_url.join(src).ok()?;
+
+    matches!(url.scheme(), "http" | "https").then(|| url.to_string())
+}
+
+/// Uses the `language-*` class of the block or its code element,
+/// limited to languages notion knows.
+fn code_language(element: ElementRef) -> String {
+    let code = Selector::parse("code").unwrap();
+    let classes = [Some(element), element.select(&code).next()]
+        .into_iter()
+        .flatten()
+        .filter_map(|element| element.value().attr("class"))
+        .collect::<Vec<&str>>()
+        .join(" ");
+
+    let language = classes
+        .split_whitespace()
+        .find_map(|class| {
+            class
+                .strip_prefix("language-")
+                .or(class.strip_prefix("lang-"))
+        })
+        .unwrap_or("")
+        .to_lowercase();
+
+    match language.as_str() {
+        "js" => "javascript",
+        "ts" => "typescript",
+        "py" => "python",
+        "rs" => "rust",
+        "sh" | "zsh" => "shell",
+        "cpp" => "c++",
+        "csharp" | "cs" => "c#",
+        "yml" => "yaml",
+        "bash" | "c" | "c#" | "c++" | "css" | "dockerfile" | "go" | "html" | "java"
+        | "javascript" | "json" | "kotlin" | "markdown" | "php" | "python" | "ruby" | "rust"
+        | "scala" | "shell" | "sql" | "swift" | "typescript" | "xml" | "yaml" => &language,
+        _ => "plain text",
+    }
+    .to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PAGE: &str = r#"<html><head><title>Fallback title</title>
+        <meta property="og:title" content="How to bake bread"></head>
+        <body>
+        <nav><a href="/">Home</a> <a href="/blog">Blog</a></nav>
+        <article>
+            <h1>How to bake bread</h1>
+            <p>Baking bread at home is easier than you think, and it only needs flour, water, salt and yeast.</p>
+            <h2>Ingredients</h2>
+            <ul><li>500g flour</li><li>10g salt</li></ul>
+            <blockquote>Bread is the warmest, kindest of all words.</blockquote>
+            <p>Mix everything, let it rise for a few hours, then bake it at 230 degrees for about 40 minutes.</p>
+            <img src="/images/bread.jpg">
+            <pre><code class="language-sh">echo done</code></pre>
+        </article>
+        <div class="comments"><p>Great recipe, I tried it yesterday and it was delicious, thanks a lot!</p></div>
+        </body></html>"#;
+
+    #[test]
+    fn extracts_article_content() {
+        let article = extract_article(PAGE, "https://example.com/blog/bread").unwrap();
+
+        assert_eq!(Some("How to bake bread".to_string()), article.title);
+        assert_eq!(
+            vec![
+                ContentBlock::Heading {
+                    level: 1,
+                    text: "How to bake bread".to_string()
+                },
+                ContentBlock::Paragraph("Baking bread at home is easier than you think, and it only needs flour, water, salt and yeast.".to_string()),
+                ContentBlock::Heading {
+                    level: 2,
+                    text: "Ingredients".to_string()
+                },
+                ContentBlock::BulletedListItem("500g flour".to_string()),
+                ContentBlock::BulletedListItem("10g salt".to_string()),
+                ContentBlock::Quote("Bread is the warmest, kindest of all words.".to_string()),
+                ContentBlock::Paragraph("Mix everything, let it rise for a few hours, then bake it at 230 degrees for about 40 minutes.".to_string()),
+                ContentBlock::Image(PageFile::External(
+                    "https://example.com/images/bread.jpg".to_string()
+                )),
+                ContentBlock::Code {
+                    language: "shell".to_string(),
+                    text: "echo done".to_string()
+                },
+            ],
+            article.blocks
+        );
+    }
+
+    #[test]
+    fn layout_wrappers_are_not_unlikely() {
+        let page = r#"<html><body>
+            <div class="layout-with-sidebar"><div id="main-menu-offset">
+                <div class="post">
+                    <p>Baking bread at home is easier than you think, and it only needs flour, water, salt and yeast.</p>
+                </div>
+                <div class="sidebar-widgets">
+                    <p>Sign up for more recipes, tips, tricks, and news from our kitchen, every week.</p>
+                </div>
+            </div></div>
+            </body></html>"#;
+
+        let article = extract_article(page, "https://example.com/blog/bread").unwrap();
+
+        assert_eq!(
+            vec![ContentBlock::Paragraph("Baking bread at home is easier than you think, and it only needs flour, water, salt and yeast.".to_string())],
+            article.blocks
+        );
+    }
+}
@@ -0,0 +1,162 @@
+//! Keeps the links users send from reaching the network the bot runs in,
+//! e.g. the cloud metadata service or an admin panel on localhost.
+
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr};
+
+use reqwest::{
+    dns::{Addrs, Name, Resolve, Resolving},
+    redirect,
+};
+use url::{Host, Url};
+
+use crate::error::{BotError, Result};
+
+const MAX_REDIRECTS: usize = 10;
+
+/// Whether the address is reachable from the internet,
+/// loopback, private, link-local and reserved addresses are not.
+pub fn is_public_address(ip: IpAddr) -> bool {
+    match ip {
+        IpAddr::V4(ip) => is_public_v4(ip),
+        IpAddr::V6(ip) => match ip.to_ipv4_mapped() {
+            Some(ip) => is_public_v4(ip),
+            None => is_public_v6(ip),
+        },
+    }
+}
+
+fn is_public_v4(ip: Ipv4Addr) -> bool {
+    let [a, b, ..] = ip.octets();
+
+    !(ip.is_unspecified()
+        || ip.is_loopback()
+        || ip.is_private()
+        || ip.is_link_local()
+        || ip.is_broadcast()
+        || ip.is_documentation()
+        || ip.is_multicast()
+        // "this network", shared address space of carrier-grade NAT, benchmarking and reserved
+        || a == 0
+        || (a == 100 && (64..128).contains(&b))
+        || (a == 198 && (18..20).contains(&b))
+        || a >= 240)
+}
+
+fn is_public_v6(ip: Ipv6Addr) -> bool {
+    let segments = ip.segments();
+
+    // NAT64 addresses reach the embedded ipv4 address
+    if segments[..6] == [0x64, 0xff9b, 0, 0, 0, 0] {
+        let [_, _, _, _, _, _, high, low] = segments;
+        return is_public_v4(Ipv4Addr::from((u32::from(high) << 16) | u32::from(low)));
+    }
+
+    !(ip.is_unspecified()
+        || ip.is_loopback()
+        || ip.is_multicast()
+        // unique local, link-local and documentation
+        || (segments[0] & 0xfe00) == 0xfc00
+        || (segments[0] & 0xffc0) == 0xfe80
+        || segments[..2] == [0x2001, 0xdb8])
+}
+
+fn is_allowed(ip: IpAddr) -> bool {
+    // the tests serve pages from localhost
+    is_public_address(ip) || (cfg!(test) && ip.is_loopback())
+}
+
+/// Rejects urls that are not http or point to a non-public ip address.
+/// Hostnames are checked when they are resolved, see `PublicResolver`.
+pub fn check_url(url: &Url) -> Result<()> {
+    if !matches!(url.scheme(), "http" | "https") {
+        return Err(BotError::WebPage(format!(
+            "unsupported scheme {}",
+            url.scheme()
+        )));
+    }
+
+    let ip = match url.host() {
+        Some(Host::Ipv4(ip)) => IpAddr::V4(ip),
+        Some(Host::Ipv6(ip)) => IpAddr::V6(ip),
+        Some(Host::Domain(_)) => return Ok(()),
+        None => return Err(BotError::WebPage("url has no host".to_string())),
+    };
+
+    if is_allowed(ip) {
+        Ok(())
+    } else {
+        Err(BotError::WebPage(format!("{ip} is not a public address")))
+    }
+}
+
+/// Follows redirects only to urls that pass `check_url`.
+pub fn redirect_policy() -> redirect::Policy {
+    redirect::Policy::custom(|attempt| {
+        if attempt.previous().len() >= MAX_REDIRECTS {
+            return attempt.error("too many redirects");
+        }
+
+        match check_url(attempt.url()) {
+            Ok(()) => attempt.follow(),
+            Err(err) => attempt.error(err),
+        }
+    })
+}
+
+/// Resolves hostnames to their public addresses only,
+/// so a domain pointing to 127.0.0.1 is rejected like the address itself.
+pub struct PublicResolver;
+
+impl Resolve for PublicResolver {
+    fn resolve(&self, name: Name) -> Resolving {
+        Box::pin(async move {
+            let addresses: Vec<SocketAddr> = tokio::net::lookup_host((name.as_str(), 0))
+                .await?
+                .filter(|address| is_allowed(address.ip()))
+                .collect();
+
+            if addresses.is_empty() {
+                return Err(format!("{} has no public address", name.as_str()).into());
+            }
+
+            Ok(Box::new(addresses.into_iter()) as Addrs)
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn private_addresses_are_rejected() {
+        for ip in [
+            "10.0.0.1",
+            "172.16.5.4",
+            "192.168.1.1",
+            "169.254.169.254",
+            "100.64.0.1",
+            "0.0.0.0",
+            "::1",
+            "fd00::1",
+            "fe80::1",
+            "::ffff:192.168.1.1",
+            "64:ff9b::a9fe:a9fe",
+        ] {
+            assert!(!is_public_address(ip.parse().unwrap()), "{ip}");
+        }
+
+        assert!(is_public_address("93.184.216.34".parse().unwrap()));
+        assert!(is_public_address("2606:4700::1111".parse().unwrap()));
+    }
+
+    #[test]
+    fn urls_of_private_addresses_are_rejected() {
+        let check = |url: &str| check_url(&Url::parse(url).unwrap());
+
+        assert!(check("http://169.254.169.254/latest/meta-data/").is_err());
+        assert!(check("http://[fd00::1]:8080/").is_err());
+        assert!(check("file:///etc/passwd").is_err());
+        assert!(check("https://example.com/article").is_ok());
+    }
+}
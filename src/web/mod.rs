@@ -0,0 +1,139 @@
+use std::{sync::Arc, time::Duration};
+
+use reqwest::{RequestBuilder, Response};
+use serde::de::DeserializeOwned;
+
+use crate::error::{BotError, Result};
+
+mod article;
+mod guard;
+mod snapshot;
+mod social;
+#[cfg(test)]
+pub mod test_server;
+mod video;
+
+pub use article::{extract_article, Article};
+pub use snapshot::snapshot;
+pub use social::{fetch_post, SocialEndpoints, SocialPost};
+pub use video::{fetch_video, format_duration, Video, VideoEndpoints};
+
+const FETCH_TIMEOUT: Duration = Duration::from_secs(15);
+/// larger pages are cut off
+const MAX_PAGE_SIZE: usize = 5 * 1024 * 1024;
+/// link shorteners are asked while the message is handled
+const REDIRECT_TIMEOUT: Duration = Duration::from_secs(5);
+const USER_AGENT: &str = "Mozilla/5.0 (compatible; add_to_notion_bot_oxid)";
+
+/// Only reaches public addresses, also when redirected, see `send`.
+fn http_client() -> Result<reqwest::Client> {
+    reqwest::Client::builder()
+        .timeout(FETCH_TIMEOUT)
+        .user_agent(USER_AGENT)
+        .redirect(guard::redirect_policy())
+        .dns_resolver(Arc::new(guard::PublicResolver))
+        .build()
+        .map_err(|err| BotError::WebPage(err.to_string()))
+}
+
+fn web_error(err: reqwest::Error) -> BotError {
+    BotError::WebPage(err.without_url().to_string())
+}
+
+/// Sends a request of `http_client`, links to ip addresses that are not public are rejected.
+/// Every request for a link of a user goes through here.
+async fn send(request: RequestBuilder) -> Result<Response> {
+    let (client, request) = request.build_split();
+    let request = request.map_err(web_error)?;
+    guard::check_url(request.url())?;
+
+    client
+        .execute(request)
+        .await
+        .and_then(|resp| resp.error_for_status())
+        .map_err(web_error)
+}
+
+/// Follows the redirects of a link, e.g. of a link shortener, and returns where they end.
+pub async fn resolve_redirects(url: &str) -> Result<String> {
+    let resp = send(http_client()?.get(url).timeout(REDIRECT_TIMEOUT)).await?;
+
+    Ok(resp.url().to_string())
+}
+
+/// Sends the request and reads the json response, e.g. of an oEmbed endpoint.
+async fn get_json<T: DeserializeOwned>(request: RequestBuilder) -> Result<T> {
+    send(request).await?.json().await.map_err(web_error)
+}
+
+/// Downloads a html page, other content types are rejected.
+pub async fn fetch_html(url: &str) -> Result<String> {
+    let mut resp = send(http_client()?.get(url)).await?;
+
+    let content_type = resp
+        .headers()
+        .get(reqwest::header::CONTENT_TYPE)
+        .and_then(|value| value.to_str().ok())
+        .unwrap_or("");
+    if !content_type.starts_with("text/html") && !content_type.starts_with("application/xhtml") {
+        return Err(BotError::WebPage(format!(
+            "not a html page: {content_type}"
+        )));
+    }
+
+    let mut content = vec![];
+    while let Some(chunk) = resp.chunk().await.map_err(web_error)? {
+        content.extend_from_slice(&chunk);
+        if content.len() > MAX_PAGE_SIZE {
+            content.truncate(MAX_PAGE_SIZE);
+            break;
+        }
+    }
+
+    Ok(String::from_utf8_lossy(&content).into_owned())
+}
+
+/// Downloads a file, e.g. an image, and returns its content type and content.
+pub async fn fetch_file(url: &str, max_size: usize) -> Result<(String, Vec<u8>)> {
+    let mut resp = send(http_client()?.get(url)).await?;
+
+    if resp.content_length().unwrap_or(0) as usize > max_size {
+        return Err(BotError::WebPage("file is too large".to_string()));
+    }
+    let content_type = resp
+        .headers()
+        .get(reqwest::header::CONTENT_TYPE)
+        .and_then(|value| value.to_str().ok())
+        .unwrap_or("application/octet-stream")
+        .to_string();
+
+    // chunked responses have no length, so the size is checked while reading
+    let mut content = vec![];
+    while let Some(chunk) = resp.chunk().await.map_err(web_error)? {
+        content.extend_from_slice(&chunk);
+        if content.len() > max_size {
+            return Err(BotError::WebPage("file is too large".to_string()));
+        }
+    }
+
+    Ok((content_type, content))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn large_files_are_rejected_while_reading() {
+        let url = test_server::serve_raw(
+            "HTTP/1.1 200 OK\r\ncontent-type: image/png\r\ntransfer-encoding: chunked\r\nconnection: close\r\n\r\n8\r\n01234567\r\n8\r\n89abcdef\r\n0\r\n\r\n",
+        )
+        .await;
+
+        assert_eq!(
+            ("image/png".to_string(), b"0123456789abcdef".to_vec()),
+            fetch_file(&url, 16).await.unwrap()
+        );
+        assert!(fetch_file(&url, 10).await.is_err());
+    }
+}
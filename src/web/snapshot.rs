@@ -0,0 +1,455 @@
+use std::{collections::HashMap, sync::Arc, time::Duration};
+
+use base64::{engine::general_purpose::STANDARD, Engine};
+use regex::{Captures, Regex};
+use scraper::{ElementRef, Html, Node, Selector};
+use tokio::{sync::Semaphore, task::JoinSet};
+use url::Url;
+
+use super::{fetch_file, fetch_html};
+use crate::error::{BotError, Result};
+
+/// more stylesheets and images are left as links to the original page
+const MAX_INLINED_RESOURCES: usize = 50;
+const MAX_RESOURCE_SIZE: usize = 2 * 1024 * 1024;
+const PARALLEL_FETCHES: usize = 8;
+/// for all stylesheets and images together, the rest stays linked
+const RESOURCES_TIMEOUT: Duration = Duration::from_secs(20);
+
+/// The snapshot is served from the image host, so nothing in it may run:
+/// only these elements are kept, unknown ones are replaced by their content.
+const ALLOWED_ELEMENTS: [&str; 62] = [
+    "html",
+    "head",
+    "body",
+    "title",
+    "style",
+    "link",
+    "img",
+    "a",
+    "div",
+    "span",
+    "p",
+    "br",
+    "hr",
+    "h1",
+    "h2",
+    "h3",
+    "h4",
+    "h5",
+    "h6",
+    "ul",
+    "ol",
+    "li",
+    "dl",
+    "dt",
+    "dd",
+    "blockquote",
+    "pre",
+    "code",
+    "em",
+    "strong",
+    "b",
+    "i",
+    "u",
+    "s",
+    "small",
+    "sub",
+    "sup",
+    "mark",
+    "q",
+    "cite",
+    "abbr",
+    "time",
+    "figure",
+    "figcaption",
+    "table",
+    "caption",
+    "thead",
+    "tbody",
+    "tfoot",
+    "tr",
+    "th",
+    "td",
+    "article",
+    "section",
+    "main",
+    "header",
+    "footer",
+    "nav",
+    "aside",
+    "details",
+    "summary",
+    "picture",
+];
+/// Removed together with their content.
+const DROPPED_ELEMENTS: [&str; 20] = [
+    "script", "noscript", "iframe", "frame", "frameset", "object", "embed", "applet", "svg",
+    "math", "template", "base", "meta", "source", "video", "audio", "canvas", "input", "button",
+    "textarea",
+];
+const VOID_ELEMENTS: [&str; 4] = ["br", "hr", "img", "link"];
+/// `href` and `src` are checked separately.
+const ALLOWED_ATTRIBUTES: [&str; 12] = [
+    "class", "id", "title", "alt", "lang", "dir", "width", "height", "colspan", "rowspan", "style",
+    "datetime",
+];
+/// Also in case a browser does not know one of the removed elements.
+const CONTENT_SECURITY_POLICY: &str = "default-src 'none'; img-src data: http: https:; \
+     style-src 'unsafe-inline' http: https:; font-src data: http: https:; form-action 'none'";
+
+/// Saves the page as a single html file: scripts, event handlers and embedded content are removed,
+/// stylesheets and images are embedded so it still works once the page is gone.
+pub async fn snapshot(url: &str) -> Result<String> {
+    let base_url = Url::parse(url).map_err(|err| BotError::WebPage(err.to_string()))?;
+    let html = fetch_html(url).await?;
+    // the parsed document cannot be held across the fetches, it is not `Send`
+    let urls = resource_urls(&Html::parse_document(&html), &base_url);
+    let resources = fetch_resources(urls).await;
+
+    Ok(format!(
+        "<!-- saved from {} -->\n<!DOCTYPE html>\n{}",
+        escape(base_url.as_str()),
+        sanitize(&Html::parse_document(&html), &base_url, &resources)
+    ))
+}
+
+/// Stylesheets and images of the page, in the order they appear.
+fn resource_urls(document: &Html, base_url: &Url) -> Vec<Url> {
+    let resources = Selector::parse("link[rel~=stylesheet i][href], img[src]").unwrap();
+
+    let mut urls: Vec<Url> = vec![];
+    for element in document.select(&resources) {
+        let Some(url) = element
+            .value()
+            .attr("href")
+            .or(element.value().attr("src"))
+            .and_then(|link| base_url.join(link.trim()).ok())
+            .filter(|url| matches!(url.scheme(), "http" | "https"))
+        else {
+            continue;
+        };
+
+        if !urls.contains(&url) {
+            urls.push(url);
+        }
+        if urls.len() >= MAX_INLINED_RESOURCES {
+            break;
+        }
+    }
+
+    urls
+}
+
+/// Fetches the resources in parallel, those not done within `RESOURCES_TIMEOUT` are left out.
+async fn fetch_resources(urls: Vec<Url>) -> HashMap<Url, (String, Vec<u8>)> {
+    let semaphore = Arc::new(Semaphore::new(PARALLEL_FETCHES));
+    let mut tasks = JoinSet::new();
+    for url in urls {
+        let semaphore = semaphore.clone();
+        tasks.spawn(async move {
+            let _permit = semaphore.acquire_owned().await;
+            let resource = fetch_file(url.as_str(), MAX_RESOURCE_SIZE).await;
+            (url, resource)
+        });
+    }
+
+    let mut resources = HashMap::new();
+    let collect = async {
+        while let Some(task) = tasks.join_next().await {
+            match task {
+                Ok((url, Ok(resource))) => {
+                    resources.insert(url, resource);
+                }
+                Ok((url, Err(err))) => log::info!("could not inline {url}: {err}"),
+                Err(err) => log::warn!("fetching a resource failed: {err}"),
+            }
+        }
+    };
+    if tokio::time::timeout(RESOURCES_TIMEOUT, collect)
+        .await
+        .is_err()
+    {
+        log::info!("not all resources were fetched in time");
+    }
+
+    // the remaining tasks are aborted when `tasks` is dropped
+    resources
+}
+
+/// Writes the document again with only what is allowed, see `ALLOWED_ELEMENTS`.
+fn sanitize(
+    document: &Html,
+    base_url: &Url,
+    resources: &HashMap<Url, (String, Vec<u8>)>,
+) -> String {
+    let mut html = String::new();
+    write_element(document.root_element(), base_url, resources, &mut html);
+
+    html
+}
+
+fn write_element(
+    element: ElementRef,
+    base_url: &Url,
+    resources: &HashMap<Url, (String, Vec<u8>)>,
+    html: &mut String,
+) {
+    let name = element.value().name();
+    if DROPPED_ELEMENTS.contains(&name) {
+        return;
+    }
+
+    if !ALLOWED_ELEMENTS.contains(&name) {
+        write_children(element, base_url, resources, html);
+        return;
+    }
+
+    match name {
+        "link" => {
+            write_stylesheet(element, base_url, resources, html);
+            return;
+        }
+        "style" => {
+            let css: String = element.text().collect();
+            html.push_str(&format!("<style>{}</style>", escape_css(&css)));
+            return;
+        }
+        _ => {}
+    }
+
+    html.push('<');
+    html.push_str(name);
+    for (attribute, value) in element.value().attrs() {
+        let value = match (name, attribute) {
+            ("a", "href") => safe_link(value, base_url),
+            ("img", "src") => image_source(value, base_url, resources),
+            (_, attribute) if ALLOWED_ATTRIBUTES.contains(&attribute) => Some(value.to_string()),
+            _ => None,
+        };
+        if let Some(value) = value {
+            html.push_str(&format!(" {attribute}=\"{}\"", escape(&value)));
+        }
+    }
+    html.push('>');
+
+    // links and everything not inlined still point to the original page
+    if name == "head" {
+        html.push_str(&format!(
+            "<meta charset=\"utf-8\"><meta http-equiv=\"Content-Security-Policy\" content=\"{}\"><base href=\"{}\">",
+            escape(CONTENT_SECURITY_POLICY),
+            escape(base_url.as_str())
+        ));
+    }
+
+    if VOID_ELEMENTS.contains(&name) {
+        return;
+    }
+    write_children(element, base_url, resources, html);
+    html.push_str(&format!("</{name}>"));
+}
+
+fn write_children(
+    element: ElementRef,
+    base_url: &Url,
+    resources: &HashMap<Url, (String, Vec<u8>)>,
+    html: &mut String,
+) {
+    for child in element.children() {
+        match child.value() {
+            Node::Text(text) => html.push_str(&escape(text)),
+            Node::Element(_) => {
+                if let Some(child) = ElementRef::wrap(child) {
+                    write_element(child, base_url, resources, html);
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+/// Stylesheets are embedded, other links in the head are removed.
+fn write_stylesheet(
+    element: ElementRef,
+    base_url: &Url,
+    resources: &HashMap<Url, (String, Vec<u8>)>,
+    html: &mut String,
+) {
+    let is_stylesheet = element.value().attr("rel").is_some_and(|rel| {
+        rel.split_whitespace()
+            .any(|rel| rel.eq_ignore_ascii_case("stylesheet"))
+    });
+    let Some(css_url) = element
+        .value()
+        .attr("href")
+        .and_then(|href| base_url.join(href.trim()).ok())
+        .filter(|url| is_stylesheet && matches!(url.scheme(), "http" | "https"))
+    else {
+        return;
+    };
+
+    match resources.get(&css_url) {
+        Some((_, content)) => {
+            let css = absolute_css_urls(&String::from_utf8_lossy(content), &css_url);
+            html.push_str(&format!("<style>{}</style>", escape_css(&css)));
+        }
+        None => html.push_str(&format!(
+            "<link rel=\"stylesheet\" href=\"{}\">",
+            escape(css_url.as_str())
+        )),
+    }
+}
+
+/// Absolute http, https and mailto links, or links within the page.
+fn safe_link(href: &str, base_url: &Url) -> Option<String> {
+    let href = href.trim();
+    if href.starts_with('#') {
+        return Some(href.to_string());
+    }
+
+    // also catches `javascript:` hidden by whitespace or control characters
+    base_url
+        .join(href)
+        .ok()
+        .filter(|url| matches!(url.scheme(), "http" | "https" | "mailto"))
+        .map(String::from)
+}
+
+/// The embedded image, otherwise the absolute url of the original.
+fn image_source(
+    src: &str,
+    base_url: &Url,
+    resources: &HashMap<Url, (String, Vec<u8>)>,
+) -> Option<String> {
+    let src = src.trim();
+    if src.starts_with("data:image/") {
+        return Some(src.to_string());
+    }
+
+    let image_url = base_url
+        .join(src)
+        .ok()
+        .filter(|url| matches!(url.scheme(), "http" | "https"))?;
+
+    match resources.get(&image_url) {
+        Some((content_type, content)) if content_type.starts_with("image/") => Some(format!(
+            "data:{content_type};base64,{}",
+            STANDARD.encode(content)
+        )),
+        _ => Some(image_url.to_string()),
+    }
+}
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+/// Style content is not escaped by html, it must not close the element.
+fn escape_css(css: &str) -> String {
+    css.replace("</", "<\\/")
+}
+
+/// Relative urls in inlined stylesheets would be resolved against the page instead.
+fn absolute_css_urls(css: &str, css_url: &Url) -> String {
+    let url_reg = Regex::new(r#"url\(\s*["']?([^"')]+)["']?\s*\)"#).unwrap();
+
+    url_reg
+        .replace_all(css, |cap: &Captures| match css_url.join(cap[1].trim()) {
+            Ok(url) if !cap[1].starts_with("data:") => format!("url(\"{url}\")"),
+            _ => cap[0].to_string(),
+        })
+        .into_owned()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn css_urls_are_made_absolute() {
+        let css_url = Url::parse("https://example.com/static/style.css").unwrap();
+        let css = "body { background: url('../img/bg.png') } i { background: url(data:image/png;base64,AA) }";
+
+        assert_eq!(
+            "body { background: url(\"https://example.com/img/bg.png\") } i { background: url(data:image/png;base64,AA) }",
+            absolute_css_urls(css, &css_url)
+        );
+    }
+
+    #[test]
+    fn nothing_that_runs_is_kept() {
+        let base_url = Url::parse("https://example.com/blog/post").unwrap();
+        let html = r##"<html><head><script>alert(1)</script><meta http-equiv="refresh" content="0;url=https://evil.example">
+            <link rel="stylesheet" href="/style.css"><link rel="preload" href="/font.woff"></head>
+            <body onload="alert(2)"><p onclick="alert(3)" class="intro">Hi &lt;there&gt;</p>
+            <a href=" java&#9;script:alert(4)">x</a><a href="other">y</a><a href="#top">z</a>
+            <iframe src="https://evil.example"></iframe><object data="x.swf"></object>
+            <svg><script>alert(5)</script></svg><img src="cat.png" onerror="alert(6)">
+            <custom-element style="color: red"><b>kept</b></custom-element>
+            <style>p { color: red }</style></body></html>"##;
+
+        let sanitized = sanitize(&Html::parse_document(html), &base_url, &HashMap::new());
+
+        for removed in [
+            "alert",
+            "script",
+            "iframe",
+            "object",
+            "svg",
+            "refresh",
+            "preload",
+            "custom-element",
+        ] {
+            assert!(!sanitized.contains(removed), "{removed} in {sanitized}");
+        }
+        assert!(sanitized.contains(r#"<p class="intro">Hi &lt;there&gt;</p>"#));
+        assert!(
+            sanitized.contains(r#"<link rel="stylesheet" href="https://example.com/style.css">"#)
+        );
+        assert!(sanitized.contains(r#"<a href="https://example.com/blog/other">y</a>"#));
+        assert!(sanitized.contains(r##"<a href="#top">z</a>"##));
+        assert!(sanitized.contains(r#"<img src="https://example.com/blog/cat.png">"#));
+        assert!(sanitized.contains("<b>kept</b>"));
+        assert!(sanitized.contains("<style>p { color: red }</style>"));
+        assert!(sanitized.contains("Content-Security-Policy"));
+    }
+
+    #[test]
+    fn resources_are_embedded() {
+        let base_url = Url::parse("https://example.com/").unwrap();
+        let html = r#"<html><head><link rel="stylesheet" href="style.css"></head>
+            <body><img src="cat.png"><img src="dog.png"></body></html>"#;
+        let document = Html::parse_document(html);
+
+        assert_eq!(
+            vec![
+                base_url.join("style.css").unwrap(),
+                base_url.join("cat.png").unwrap(),
+                base_url.join("dog.png").unwrap(),
+            ],
+            resource_urls(&document, &base_url)
+        );
+
+        let resources = HashMap::from([
+            (
+                base_url.join("style.css").unwrap(),
+                (
+                    "text/css".to_string(),
+                    b"b { color: red } </style>".to_vec(),
+                ),
+            ),
+            (
+                base_url.join("cat.png").unwrap(),
+                ("image/png".to_string(), vec![1, 2, 3]),
+            ),
+        ]);
+        let sanitized = sanitize(&document, &base_url, &resources);
+
+        assert!(sanitized.contains("<style>b { color: red } <\\/style></style>"));
+        assert!(sanitized.contains(r#"<img src="data:image/png;base64,AQID">"#));
+        assert!(sanitized.contains(r#"<img src="https://example.com/dog.png">"#));
+    }
+}
@@ -0,0 +1,453 @@
+use regex::Regex;
+use serde::Deserialize;
+use serde_json::Value;
+use url::Url;
+
+use super::{get_json, http_client};
+use crate::error::{BotError, Result};
+use crate::notion::ContentBlock;
+use crate::notion::PageFile;
+
+/// posts with more images are cut off
+const MAX_POST_IMAGES: usize = 10;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum SocialSite {
+    Twitter,
+    Reddit,
+    /// any instance, confirmed by asking the api on the host of the post
+    Mastodon,
+}
+
+/// Where posts are loaded from, replaced by a local server in tests.
+#[derive(Clone, Debug, PartialEq)]
+pub struct SocialEndpoints {
+    pub twitter_syndication: String,
+    pub reddit: String,
+}
+
+impl Default for SocialEndpoints {
+    fn default() -> Self {
+        SocialEndpoints {
+            twitter_syndication: "https://cdn.syndication.twimg.com/tweet-result".to_string(),
+            reddit: "https://www.reddit.com".to_string(),
+        }
+    }
+}
+
+/// A post on a social platform, kept on the page in case it is deleted.
+#[derive(Clone, Debug, PartialEq)]
+pub struct SocialPost {
+    pub site: SocialSite,
+    /// only reddit posts have a title
+    pub title: Option<String>,
+    pub author: String,
+    pub text: String,
+    /// the attached images, linked on the platform until they are stored elsewhere
+    pub images: Vec<PageFile>,
+}
+
+impl SocialPost {
+    /// The text as quote followed by the images.
+    pub fn blocks(&self) -> Vec<ContentBlock> {
+        let quote = match &self.title {
+            Some(title) if self.text.is_empty() => format!("{title}\n— {}", self.author),
+            Some(title) => format!("{title}\n\n{}\n— {}", self.text, self.author),
+            None => format!("{}\n— {}", self.text, self.author),
+        };
+
+        [ContentBlock::Quote(quote)]
+            .into_iter()
+            .chain(self.images.iter().cloned().map(ContentBlock::Image))
+            .collect()
+    }
+}
+
+#[derive(Deserialize)]
+struct Tweet {
+    text: String,
+    user: TweetUser,
+    #[serde(default, rename = "mediaDetails")]
+    media_details: Vec<TweetMedia>,
+}
+
+#[derive(Deserialize)]
+struct TweetUser {
+    name: String,
+    screen_name: String,
+}
+
+#[derive(Deserialize)]
+struct TweetMedia {
+    /// the preview image for videos
+    media_url_https: String,
+}
+
+#[derive(Deserialize)]
+struct MastodonStatus {
+    /// html
+    content: String,
+    account: MastodonAccount,
+    #[serde(default)]
+    media_attachments: Vec<MastodonMedia>,
+}
+
+/// Only to confirm the host runs mastodon or a compatible server.
+#[derive(Deserialize)]
+struct MastodonInstance {
+    version: String,
+}
+
+#[derive(Deserialize)]
+struct MastodonAccount {
+    display_name: String,
+    acct: String,
+}
+
+#[derive(Deserialize)]
+struct MastodonMedia {
+    #[serde(rename = "type")]
+    kind: String,
+    url: String,
+    preview_url: Option<String>,
+}
+
+/// Returns the site and the id of the post the url points to.
+/// Any host may be a mastodon instance, `fetch_post` asks it before loading the post.
+pub fn find_post(url: &str) -> Option<(SocialSite, String)> {
+    let url = Url::parse(url).ok()?;
+    let host = url.host_str()?;
+    let segments: Vec<&str> = url
+        .path_segments()?
+        .filter(|segment| !segment.is_empty())
+        .collect();
+    let is_id = |segment: &str| !segment.is_empty() && segment.chars().all(|c| c.is_ascii_digit());
+
+    match (
+        host.trim_start_matches("www.")
+            .trim_start_matches("mobile."),
+        segments.as_slice(),
+    ) {
+        ("twitter.com" | "x.com", [_, "status", id, ..]) if is_id(id) => {
+            Some((SocialSite::Twitter, id.to_string()))
+        }
+        (host, ["r", _, "comments", id, ..])
+            if host == "reddit.com" || host.ends_with(".reddit.com") =>
+        {
+            Some((SocialSite::Reddit, id.to_string()))
+        }
+        ("redd.it", [id]) => Some((SocialSite::Reddit, id.to_string())),
+        (_, [user, id]) if user.starts_with('@') && is_id(id) => {
+            Some((SocialSite::Mastodon, id.to_string()))
+        }
+        (_, ["users", _, "statuses", id]) if is_id(id) => {
+            Some((SocialSite::Mastodon, id.to_string()))
+        }
+        _ => None,
+    }
+}
+
+/// Loads a post from twitter, reddit or a mastodon instance, `None` for other urls.
+pub async fn fetch_post(url: &str, endpoints: &SocialEndpoints) -> Result<Option<SocialPost>> {
+    let Some((site, id)) = find_post(url) else {
+        return Ok(None);
+    };
+    let client = http_client()?;
+
+    let post = match site {
+        SocialSite::Twitter => {
+            let tweet: Tweet = get_json(
+                client
+                    .get(&endpoints.twitter_syndication)
+                    .query(&[("id", id.as_str()), ("token", &syndication_token(&id))]),
+            )
+            .await?;
+
+            tweet_post(tweet)
+        }
+        SocialSite::Reddit => {
+            let listing: Value = get_json(client.get(format!(
+                "{}/comments/{id}.json",
+                endpoints.reddit.trim_end_matches('/')
+            )))
+            .await?;
+
+            // the post is followed by a listing of its comments
+            reddit_post(&listing[0]["data"]["children"][0]["data"])
+                .ok_or(BotError::WebPage("reddit sent no post".to_string()))?
+        }
+        SocialSite::Mastodon => {
+            let mut api_url = Url::parse(url).expect("the post was found in a valid url");
+            api_url.set_query(None);
+            api_url.set_fragment(None);
+
+            // other sites have urls like posts as well
+            api_url.set_path("/api/v1/instance");
+            match get_json::<MastodonInstance>(client.get(api_url.clone())).await {
+                Ok(instance) => log::debug!("{url} is on mastodon {}", instance.version),
+                Err(err) => {
+                    log::info!("{url} is not on a mastodon instance: {err}");
+                    return Ok(None);
+                }
+            }
+
+            api_url.set_path(&format!("/api/v1/statuses/{id}"));
+            let status: MastodonStatus = get_json(client.get(api_url)).await?;
+
+            SocialPost {
+                site,
+                title: None,
+                author: format!("{} (@{})", status.account.display_name, status.account.acct),
+                text: html_to_text(&status.content),
+                images: status
+                    .media_attachments
+                    .into_iter()
+                    .filter_map(|media| match media.kind.as_str() {
+                        "image" => Some(media.url),
+                        _ => media.preview_url,
+                    })
+                    .map(PageFile::External)
+                    .collect(),
+            }
+        }
+    };
+
+    Ok(Some(SocialPost {
+        images: post.images.into_iter().take(MAX_POST_IMAGES).collect(),
+        ..post
+    }))
+}
+
+fn tweet_post(tweet: Tweet) -> SocialPost {
+    // the links to the attached media are at the end of the text
+    let media_link_reg = Regex::new(r"\s*https://t\.co/\w+$").unwrap();
+    let mut text = decode_entities(&tweet.text);
+    if !tweet.media_details.is_empty() {
+        text = media_link_reg.replace(&text, "").into_owned();
+    }
+
+    SocialPost {
+        site: SocialSite::Twitter,
+        title: None,
+        author: format!("{} (@{})", tweet.user.name, tweet.user.screen_name),
+        text,
+        images: tweet
+            .media_details
+            .into_iter()
+            .map(|media| PageFile::External(media.media_url_https))
+            .collect(),
+    }
+}
+
+/// The post in the `data` of the first child of a reddit listing.
+fn reddit_post(data: &Value) -> Option<SocialPost> {
+    let title = data["title"].as_str()?;
+    let author = data["author"].as_str().unwrap_or("[deleted]");
+
+    let images = if data["post_hint"] == "image" {
+        data["url"]
+            .as_str()
+            .map(|url| PageFile::External(url.to_string()))
+            .into_iter()
+            .collect()
+    } else {
+        // galleries list their images in order, with the urls in the metadata
+        data["gallery_data"]["items"]
+            .as_array()
+            .into_iter()
+            .flatten()
+            .filter_map(|item| {
+                let media_id = item["media_id"].as_str()?;
+                data["media_metadata"][media_id]["s"]["u"].as_str()
+            })
+            .map(|url| PageFile::External(decode_entities(url)))
+            .collect()
+    };
+
+    Some(SocialPost {
+        site: SocialSite::Reddit,
+        title: Some(decode_entities(title)),
+        author: format!("u/{author}"),
+        text: decode_entities(data["selftext"].as_str().unwrap_or("").trim()),
+        images,
+    })
+}
+
+/// The token the twitter embed widget sends along with the id of the tweet.
+fn syndication_token(id: &str) -> String {
+    const DIGITS: &[u8] = b"0123456789abcdefghijklmnopqrstuvwxyz";
+
+    let value = id.parse::<f64>().unwrap_or(0.0) / 1e15 * std::f64::consts::PI;
+    let mut integer = value.trunc() as u64;
+    let mut fraction = value.fract();
+
+    let mut token = vec![];
+    while integer > 0 {
+        token.insert(0, DIGITS[(integer % 36) as usize]);
+        integer /= 36;
+    }
+    for _ in 0..10 {
+        fraction *= 36.0;
+        token.push(DIGITS[fraction.trunc() as usize]);
+        fraction = fraction.fract();
+    }
+
+    token
+        .into_iter()
+        .filter(|digit| *digit != b'0')
+        .map(char::from)
+        .collect()
+}
+
+/// Mastodon posts are html paragraphs with links and line breaks.
+fn html_to_text(html: &str) -> String {
+    let break_reg = Regex::new(r"(?i)<br\s*/?>").unwrap();
+    let paragraph_reg = Regex::new(r"(?i)</p>\s*<p[^>]*>").unwrap();
+    let tag_reg = Regex::new(r"<[^>]*>").unwrap();
+
+    let text = break_reg.replace_all(html, "\n");
+    let text = paragraph_reg.replace_all(&text, "\n\n");
+    let text = tag_reg.replace_all(&text, "");
+
+    decode_entities(text.trim())
+}
+
+fn decode_entities(text: &str) -> String {
+    text.replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&#39;", "'")
+        .replace("&nbsp;", " ")
+        .replace("&amp;", "&")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::test_server::{serve, serve_paths};
+    use super::*;
+
+    #[test]
+    fn finds_posts() {
+        assert_eq!(
+            Some((SocialSite::Twitter, "1234567890".to_string())),
+            find_post("https://x.com/someone/status/1234567890?s=20")
+        );
+        assert_eq!(
+            Some((SocialSite::Reddit, "1abcde".to_string())),
+            find_post("https://old.reddit.com/r/rust/comments/1abcde/some_title/")
+        );
+        assert_eq!(
+            Some((SocialSite::Mastodon, "109876543210".to_string())),
+            find_post("https://mastodon.social/@someone/109876543210")
+        );
+        assert_eq!(None, find_post("https://x.com/someone"));
+        assert_eq!(
+            None,
+            find_post("https://notreddit.com/r/rust/comments/1abcde/some_title/")
+        );
+    }
+
+    #[test]
+    fn parses_tweets() {
+        let tweet: Tweet = serde_json::from_str(
+            r#"{"text":"Rust 2024 is out &amp; it is great https://t.co/AbC123","user":{"name":"Rust","screen_name":"rustlang"},"mediaDetails":[{"media_url_https":"https://pbs.twimg.com/media/a.jpg","type":"photo"}]}"#,
+        )
+        .unwrap();
+
+        assert_eq!(
+            SocialPost {
+                site: SocialSite::Twitter,
+                title: None,
+                author: "Rust (@rustlang)".to_string(),
+                text: "Rust 2024 is out & it is great".to_string(),
+                images: vec![PageFile::External(
+                    "https://pbs.twimg.com/media/a.jpg".to_string()
+                )],
+            },
+            tweet_post(tweet)
+        );
+
+        // without media the link is part of the text
+        let tweet: Tweet = serde_json::from_str(
+            r#"{"text":"Read this https://t.co/AbC123","user":{"name":"Rust","screen_name":"rustlang"}}"#,
+        )
+        .unwrap();
+        assert_eq!("Read this https://t.co/AbC123", tweet_post(tweet).text);
+    }
+
+    #[test]
+    fn parses_reddit_posts() {
+        let listing: Value = serde_json::from_str(
+            r#"[{"data":{"children":[{"data":{"title":"Pasta &amp; more","author":"cook","selftext":" My recipes \n","post_hint":"image","url":"https://i.redd.it/a.jpg"}}]}},{"data":{"children":[]}}]"#,
+        )
+        .unwrap();
+
+        assert_eq!(
+            Some(SocialPost {
+                site: SocialSite::Reddit,
+                title: Some("Pasta & more".to_string()),
+                author: "u/cook".to_string(),
+                text: "My recipes".to_string(),
+                images: vec![PageFile::External("https://i.redd.it/a.jpg".to_string())],
+            }),
+            reddit_post(&listing[0]["data"]["children"][0]["data"])
+        );
+
+        let gallery: Value = serde_json::from_str(
+            r#"{"title":"Trip","gallery_data":{"items":[{"media_id":"b"},{"media_id":"a"}]},"media_metadata":{"a":{"s":{"u":"https://preview.redd.it/a.jpg?width=640&amp;s=1"}},"b":{"s":{"u":"https://preview.redd.it/b.jpg"}}}}"#,
+        )
+        .unwrap();
+        let post = reddit_post(&gallery).unwrap();
+        assert_eq!("u/[deleted]", post.author);
+        assert_eq!(
+            vec![
+                PageFile::External("https://preview.redd.it/b.jpg".to_string()),
+                PageFile::External("https://preview.redd.it/a.jpg?width=640&s=1".to_string()),
+            ],
+            post.images
+        );
+
+        assert_eq!(None, reddit_post(&Value::Null));
+    }
+
+    #[tokio::test]
+    async fn loads_mastodon_posts() {
+        let instance = serve_paths(vec![
+            ("/api/v1/instance", r#"{"uri":"mastodon.example","version":"4.2.0"}"#),
+            (
+                "/api/v1/statuses/123",
+                r#"{"content":"<p>Hello &amp; welcome</p><p>second<br>line</p>","account":{"display_name":"Someone","acct":"someone"},"media_attachments":[{"type":"image","url":"https://files.example/a.png","preview_url":null}]}"#,
+            ),
+        ])
+        .await;
+
+        let post = fetch_post(
+            &format!("{instance}/@someone/123"),
+            &SocialEndpoints::default(),
+        )
+        .await
+        .unwrap()
+        .unwrap();
+
+        assert_eq!("Someone (@someone)", post.author);
+        assert_eq!("Hello & welcome\n\nsecond\nline", post.text);
+        assert_eq!(
+            vec![PageFile::External(
+                "https://files.example/a.png".to_string()
+            )],
+            post.images
+        );
+        assert_eq!(2, post.blocks().len());
+    }
+
+    #[tokio::test]
+    async fn other_sites_are_not_asked_for_mastodon_posts() {
+        let site = serve("<html>a blog</html>").await;
+
+        let post = fetch_post(&format!("{site}/@someone/123"), &SocialEndpoints::default())
+            .await
+            .unwrap();
+
+        assert_eq!(None, post);
+    }
+}
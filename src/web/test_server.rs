@@ -0,0 +1,105 @@
+//! A local http server standing in for the sites and apis the bot loads from.
+
+use std::sync::{Arc, Mutex};
+
+use tokio::{
+    io::{AsyncReadExt, AsyncWriteExt},
+    net::{TcpListener, TcpStream},
+};
+
+/// Answers every request with the body.
+pub async fn serve(body: &'static str) -> String {
+    serve_paths(vec![("/", body)]).await
+}
+
+/// Answers requests with the body of the first path their path starts with, others with 404.
+pub async fn serve_paths(paths: Vec<(&'static str, &'static str)>) -> String {
+    let (address, _) = serve_recorded(paths).await;
+
+    address
+}
+
+/// Answers every request with the raw response, e.g. to send a chunked body.
+pub async fn serve_raw(response: &'static str) -> String {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let address = listener.local_addr().unwrap();
+
+    tokio::spawn(async move {
+        while let Ok((mut socket, _)) = listener.accept().await {
+            read_request(&mut socket).await;
+            let _ = socket.write_all(response.as_bytes()).await;
+        }
+    });
+
+    format!("http://{address}")
+}
+
+/// Like `serve_paths`, also returns the requests received so far,
+/// as method and path followed by the body on the next line, e.g. `POST /pages\n{…}`.
+pub async fn serve_recorded(
+    paths: Vec<(&'static str, &'static str)>,
+) -> (String, Arc<Mutex<Vec<String>>>) {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let address = listener.local_addr().unwrap();
+    let requests = Arc::new(Mutex::new(vec![]));
+
+    let received = requests.clone();
+    tokio::spawn(async move {
+        while let Ok((mut socket, _)) = listener.accept().await {
+            let request = read_request(&mut socket).await;
+            // POST /path?query HTTP/1.1
+            let mut request_line = request.split_whitespace();
+            let method = request_line.next().unwrap_or("GET");
+            let path = request_line.next().unwrap_or("/");
+            let body = request.split_once("\r\n\r\n").map_or("", |(_, body)| body);
+            received
+                .lock()
+                .unwrap()
+                .push(format!("{method} {path}\n{body}"));
+
+            let response = match paths.iter().find(|(prefix, _)| path.starts_with(prefix)) {
+                Some((_, body)) => format!(
+                    "HTTP/1.1 200 OK\r\ncontent-type: application/json\r\ncontent-length: {}\r\nconnection: close\r\n\r\n{body}",
+                    body.len()
+                ),
+                None => "HTTP/1.1 404 Not Found\r\ncontent-length: 0\r\nconnection: close\r\n\r\n"
+                    .to_string(),
+            };
+            let _ = socket.write_all(response.as_bytes()).await;
+        }
+    });
+
+    (format!("http://{address}"), requests)
+}
+
+/// Reads the head and the body of the request, so the connection is not reset while answering.
+async fn read_request(socket: &mut TcpStream) -> String {
+    let mut request = vec![];
+    let mut buffer = [0; 4096];
+
+    loop {
+        let length = socket.read(&mut buffer).await.unwrap_or(0);
+        if length == 0 {
+            break;
+        }
+        request.extend_from_slice(&buffer[..length]);
+
+        let text = String::from_utf8_lossy(&request);
+        let Some(head_end) = text.find("\r\n\r\n") else {
+            continue;
+        };
+        let content_length = text[..head_end]
+            .lines()
+            .find_map(|line| {
+                let (name, value) = line.split_once(':')?;
+                name.eq_ignore_ascii_case("content-length")
+                    .then(|| value.trim().parse::<usize>().ok())?
+            })
+            .unwrap_or(0);
+        if request.len() >= head_end + 4 + content_length {
+            break;
+        }
+    }
+
+    String::from_utf8_lossy(&request).into_owned()
+}
@@ -0,0 +1,235 @@
+use regex::Regex;
+use serde::Deserialize;
+use url::Url;
+
+use super::{get_json, http_client, send, web_error};
+use crate::error::Result;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum VideoSite {
+    YouTube,
+    Vimeo,
+}
+
+/// Where video details are loaded from, replaced by a local server in tests.
+#[derive(Clone, Debug, PartialEq)]
+pub struct VideoEndpoints {
+    pub youtube_oembed: String,
+    pub vimeo_oembed: String,
+    /// the oEmbed of youtube has no duration, it is taken from the video page
+    pub youtube_watch: String,
+}
+
+impl Default for VideoEndpoints {
+    fn default() -> Self {
+        VideoEndpoints {
+            youtube_oembed: "https://www.youtube.com/oembed".to_string(),
+            vimeo_oembed: "https://vimeo.com/api/oembed.json".to_string(),
+            youtube_watch: "https://www.youtube.com/watch".to_string(),
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Video {
+    pub site: VideoSite,
+    pub title: String,
+    /// the channel on youtube
+    pub author: Option<String>,
+    /// in seconds
+    pub duration: Option<u64>,
+    pub thumbnail_url: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct OEmbedResponse {
+    title: String,
+    author_name: Option<String>,
+    duration: Option<u64>,
+    thumbnail_url: Option<String>,
+}
+
+/// Returns the site and the id of the video the url points to.
+pub fn find_video(url: &str) -> Option<(VideoSite, String)> {
+    let url = Url::parse(url).ok()?;
+    let host = url
+        .host_str()?
+        .trim_start_matches("www.")
+        .trim_start_matches("m.");
+    let mut segments = url.path_segments()?.filter(|segment| !segment.is_empty());
+
+    match host {
+        "youtube.com" | "music.youtube.com" => match segments.next()? {
+            "watch" => url
+                .query_pairs()
+                .find(|(key, _)| key == "v")
+                .map(|(_, id)| (VideoSite::YouTube, id.into_owned())),
+            "shorts" | "live" | "embed" => Some((VideoSite::YouTube, segments.next()?.to_string())),
+            _ => None,
+        },
+        "youtu.be" => Some((VideoSite::YouTube, segments.next()?.to_string())),
+        "vimeo.com" | "player.vimeo.com" => segments
+            .find(|segment| segment.chars().all(|c| c.is_ascii_digit()))
+            .map(|id| (VideoSite::Vimeo, id.to_string())),
+        _ => None,
+    }
+}
+
+/// Loads the details of a youtube or vimeo video, `None` for other urls.
+pub async fn fetch_video(url: &str, endpoints: &VideoEndpoints) -> Result<Option<Video>> {
+    let Some((site, id)) = find_video(url) else {
+        return Ok(None);
+    };
+
+    let oembed_url = match site {
+        VideoSite::YouTube => &endpoints.youtube_oembed,
+        VideoSite::Vimeo => &endpoints.vimeo_oembed,
+    };
+    let client = http_client()?;
+    let oembed: OEmbedResponse = get_json(
+        client
+            .get(oembed_url)
+            .query(&[("url", url), ("format", "json")]),
+    )
+    .await?;
+
+    let duration = match (site, oembed.duration) {
+        (_, Some(duration)) => Some(duration),
+        (VideoSite::YouTube, None) => {
+            // the video is still saved without a duration
+            match youtube_duration(&client, &endpoints.youtube_watch, &id).await {
+                Ok(duration) => duration,
+                Err(err) => {
+                    log::info!("could not load duration of youtube video {id}: {err}");
+                    None
+                }
+            }
+        }
+        (VideoSite::Vimeo, None) => None,
+    };
+
+    Ok(Some(Video {
+        site,
+        title: oembed.title,
+        author: oembed.author_name,
+        duration,
+        thumbnail_url: oembed.thumbnail_url,
+    }))
+}
+
+async fn youtube_duration(
+    client: &reqwest::Client,
+    watch_url: &str,
+    id: &str,
+) -> Result<Option<u64>> {
+    let page = send(client.get(watch_url).query(&[("v", id)]))
+        .await?
+        .text()
+        .await
+        .map_err(web_error)?;
+
+    let duration_reg = Regex::new(r#""lengthSeconds":"(\d+)""#).unwrap();
+    Ok(duration_reg
+        .captures(&page)
+        .and_then(|cap| cap[1].parse().ok()))
+}
+
+/// `1:02:03` or `4:05`
+pub fn format_duration(seconds: u64) -> String {
+    let (hours, minutes, seconds) = (seconds / 3600, seconds / 60 % 60, seconds % 60);
+
+    if hours > 0 {
+        format!("{hours}:{minutes:02}:{seconds:02}")
+    } else {
+        format!("{minutes}:{seconds:02}")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::test_server::serve;
+    use super::*;
+
+    #[test]
+    fn finds_videos() {
+        assert_eq!(
+            Some((VideoSite::YouTube, "dQw4w9WgXcQ".to_string())),
+            find_video("https://www.youtube.com/watch?v=dQw4w9WgXcQ&t=42")
+        );
+        assert_eq!(
+            Some((VideoSite::YouTube, "dQw4w9WgXcQ".to_string())),
+            find_video("https://youtu.be/dQw4w9WgXcQ?si=abc")
+        );
+        assert_eq!(
+            Some((VideoSite::Vimeo, "76979871".to_string())),
+            find_video("https://vimeo.com/76979871")
+        );
+        assert_eq!(None, find_video("https://www.youtube.com/@channel"));
+    }
+
+    #[tokio::test]
+    async fn loads_video_details() {
+        let oembed = serve(
+            r#"{"type":"video","title":"The New Vimeo Player","author_name":"Vimeo Staff","duration":62,"thumbnail_url":"https://i.vimeocdn.com/video/452001751-640.jpg"}"#,
+        )
+        .await;
+        let endpoints = VideoEndpoints {
+            vimeo_oembed: oembed,
+            ..VideoEndpoints::default()
+        };
+
+        let video = fetch_video("https://vimeo.com/76979871", &endpoints)
+            .await
+            .unwrap()
+            .unwrap();
+
+        assert_eq!("The New Vimeo Player", video.title);
+        assert_eq!(Some("Vimeo Staff".to_string()), video.author);
+        assert_eq!(Some(62), video.duration);
+        assert_eq!("1:02", format_duration(62));
+    }
+
+    #[tokio::test]
+    async fn loads_youtube_duration_from_the_video_page() {
+        let oembed = serve(
+            r#"{"type":"video","title":"Never Gonna Give You Up","author_name":"Rick Astley","thumbnail_url":"https://i.ytimg.com/vi/dQw4w9WgXcQ/hqdefault.jpg"}"#,
+        )
+        .await;
+        let watch = serve(r#"<script>var ytInitialPlayerResponse = {"videoDetails":{"videoId":"dQw4w9WgXcQ","lengthSeconds":"212"}};</script>"#).await;
+        let endpoints = VideoEndpoints {
+            youtube_oembed: oembed,
+            youtube_watch: watch,
+            ..VideoEndpoints::default()
+        };
+
+        let video = fetch_video("https://youtu.be/dQw4w9WgXcQ", &endpoints)
+            .await
+            .unwrap()
+            .unwrap();
+
+        assert_eq!(VideoSite::YouTube, video.site);
+        assert_eq!("Never Gonna Give You Up", video.title);
+        assert_eq!(Some("Rick Astley".to_string()), video.author);
+        assert_eq!(Some(212), video.duration);
+    }
+
+    #[tokio::test]
+    async fn youtube_videos_without_duration_are_kept() {
+        let oembed = serve(r#"{"type":"video","title":"Never Gonna Give You Up"}"#).await;
+        let watch = serve("<html>consent page</html>").await;
+        let endpoints = VideoEndpoints {
+            youtube_oembed: oembed,
+            youtube_watch: watch,
+            ..VideoEndpoints::default()
+        };
+
+        let video = fetch_video("https://www.youtube.com/watch?v=dQw4w9WgXcQ", &endpoints)
+            .await
+            .unwrap()
+            .unwrap();
+
+        assert_eq!("Never Gonna Give You Up", video.title);
+        assert_eq!(None, video.author);
+        assert_eq!(None, video.duration);
+    }
+}